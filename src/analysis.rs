@@ -0,0 +1,534 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+
+use super::game::Cell;
+use super::game::Element;
+use super::game::GameState;
+use super::game::Move;
+use super::game::Piece;
+use super::game::Solution;
+use super::hex::Hex;
+use super::transposition::TranspositionTable;
+
+// stop counting solutions once there are this many, full boards can have millions
+const SOLUTION_CAP: u64 = 10000;
+// how many distinct positions difficulty will look at when counting dead ends
+const DEFAULT_NODE_BUDGET: u64 = 200000;
+
+// search statistics that say how forgiving a board is
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difficulty{
+    pub solvable: bool,
+    // distinct solutions, counting orderings of independent moves as one. stops at SOLUTION_CAP.
+    pub solutions: u64,
+    // positions with pieces left but no legal moves
+    pub dead_ends: u64,
+    // distinct positions searched while counting dead ends
+    pub positions: u64,
+    // false if the node budget ran out before every position was seen
+    pub complete: bool,
+    // how many legal moves there were at each step of the first solution found
+    pub branching: Vec<usize>,
+    // legal moves along the first solution that lead to a position that can't be cleared
+    pub fatal_moves: usize,
+    // the first step (from 0) of that solution where a legal move would lose the game
+    pub first_fatal_step: Option<usize>,
+    // salt that has to be spent on elements because of odd element counts
+    pub forced_salt: usize,
+    // salt spent on elements in the first solution
+    pub salt_on_elements: usize,
+}
+
+impl Difficulty{
+    pub fn mean_branching(&self) -> f64{
+        if self.branching.is_empty(){
+            0.0
+        }else{
+            self.branching.iter().sum::<usize>() as f64 / self.branching.len() as f64
+        }
+    }
+
+    // the share of legal moves along the solution that lose, 0 for boards where nothing can go wrong
+    pub fn fatal_ratio(&self) -> f64{
+        let total: usize = self.branching.iter().sum();
+        if total == 0{
+            0.0
+        }else{
+            self.fatal_moves as f64 / total as f64
+        }
+    }
+}
+
+// what best_partial_clearing tries to get furthest with
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ClearingGoal{
+    FewestPieces, // ties go to more metals taken
+    FurthestMetal // ties go to fewer pieces left
+}
+
+// why a piece is still on the board when no moves are left
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stranded{
+    NoPartner, // nothing left on the board can pair with it
+    LockedMetal(u8), // a metal waiting for the given metal to be taken first
+    Covered, // it has partners left, but too many neighbours to be open
+    PartnersCovered // it is open, but none of its partners are
+}
+
+impl fmt::Display for Stranded{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        match self{
+            Stranded::NoPartner => write!(f, "nothing left to pair with"),
+            Stranded::LockedMetal(m) => write!(f, "locked until metal {} is taken", m),
+            Stranded::Covered => write!(f, "not open"),
+            Stranded::PartnersCovered => write!(f, "no open partner"),
+        }
+    }
+}
+
+// the best sequence of moves found for a board that may not be solvable
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartialClearing{
+    pub moves: Solution,
+    pub pieces_left: usize,
+    pub metals_taken: u8,
+    // every piece left after the moves, with the reason it can't be removed
+    pub stranded: Vec<(Cell, Stranded)>,
+    // false if the node budget ran out, so a better sequence might exist
+    pub complete: bool
+}
+
+impl fmt::Display for PartialClearing{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        writeln!(f, "{} moves leave {} pieces with {} metals taken{}", self.moves.len(), self.pieces_left,
+            self.metals_taken, if self.complete{ "" }else{ " (search was cut short)" })?;
+        write!(f, "{}", self.moves)?;
+        for (c, reason) in self.stranded.iter(){
+            writeln!(f, "{}: {}", c, reason)?;
+        }
+        Ok(())
+    }
+}
+
+// how stuck one piece is, and how much freedom the solutions give it
+#[derive(Clone, Debug, PartialEq)]
+pub struct PieceAnalysis{
+    pub cell: Cell,
+    // the fewest neighbours that have to go before is_open would let it be taken, 0 if it
+    // is open already (metals can still be locked behind the rest of the chain)
+    pub blockers: usize,
+    // every piece it is paired with in some solution that was looked at
+    pub partners: Vec<Cell>,
+    // removed by the same move in every solution that was looked at
+    pub critical: bool
+}
+
+// the pieces a board hinges on
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockingAnalysis{
+    pub pieces: Vec<PieceAnalysis>,
+    // moves that every solution makes at some point
+    pub forced_pairings: Vec<Move>,
+    // solutions looked at, counting orderings of independent moves as one. stops at SOLUTION_CAP.
+    pub solutions: u64,
+    // false if there were more solutions than that, so less may really be forced
+    pub complete: bool
+}
+
+impl GameState{
+    pub fn difficulty(&self) -> Difficulty{
+        self.difficulty_with_budget(DEFAULT_NODE_BUDGET)
+    }
+
+    // like difficulty, but looks at no more than node_budget positions when counting dead ends
+    pub fn difficulty_with_budget(&self, node_budget: u64) -> Difficulty{
+        // the search plays moves, so work on a copy to leave this board's undo history alone
+        let mut gs = self.clone();
+        let mut report = Difficulty{
+            solvable: false,
+            solutions: 0,
+            dead_ends: 0,
+            positions: 0,
+            complete: true,
+            branching: Vec::new(),
+            fatal_moves: 0,
+            first_fatal_step: None,
+            forced_salt: 0,
+            salt_on_elements: 0,
+        };
+
+        let inventory = gs.inventory();
+        report.forced_salt = [Element::Water, Element::Fire, Element::Earth, Element::Air].iter()
+            .filter(|e| inventory.get(&Piece::Element(**e)).unwrap_or(&0) % 2 == 1)
+            .count();
+
+        let mut found = 0;
+        gs.for_each_solution(true, |_| {
+            found += 1;
+            found < SOLUTION_CAP
+        });
+        report.solutions = found;
+
+        let mut seen = HashSet::new();
+        gs.count_dead_ends(&mut seen, node_budget, &mut report);
+
+        // walk down the first solution, trying every other move on the way
+        let mut table = TranspositionTable::default();
+        if let Some(solution) = gs.solve_with_table(&mut table){
+            report.solvable = true;
+            for (step, action) in solution.iter().enumerate(){
+                let moves = gs.legal_moves();
+                report.branching.push(moves.len());
+                for m in moves.iter(){
+                    gs.apply_move(m).expect("Legal move was rejected");
+                    let lost = gs.solve_with_table(&mut table).is_none();
+                    gs.undo();
+                    if lost{
+                        report.fatal_moves += 1;
+                        if report.first_fatal_step.is_none(){
+                            report.first_fatal_step = Some(step);
+                        }
+                    }
+                }
+
+                if let Move::Pair(a, b) = action{
+                    let salted = matches!((a.piece, b.piece),
+                        (Piece::Salt, Piece::Element(_)) | (Piece::Element(_), Piece::Salt));
+                    if salted{
+                        report.salt_on_elements += 1;
+                    }
+                }
+                gs.apply_move(action).expect("Solution move was rejected");
+            }
+        }
+
+        report
+    }
+
+    // finds the moves that get furthest towards clearing the board, for boards that solve
+    // can't clear (usually because they were misread)
+    pub fn best_partial_clearing(&self, goal: ClearingGoal) -> PartialClearing{
+        self.best_partial_clearing_with_budget(goal, DEFAULT_NODE_BUDGET)
+    }
+
+    pub fn best_partial_clearing_with_budget(&self, goal: ClearingGoal, node_budget: u64) -> PartialClearing{
+        let mut gs = self.clone();
+        let mut seen = HashSet::new();
+        let mut path = Vec::new();
+        let mut best = (gs.clearing_score(goal), Vec::new());
+        let complete = gs.search_partial(goal, &mut seen, node_budget, &mut path, &mut best);
+
+        let mut end = self.clone();
+        let moves = Solution::from(best.1);
+        moves.replay(&mut end).expect("Partial clearing has an illegal move");
+        PartialClearing{
+            pieces_left: end.inventory().values().sum(),
+            metals_taken: end.metals_taken(),
+            stranded: end.stranded_pieces(),
+            moves,
+            complete
+        }
+    }
+
+    // smaller is better
+    fn clearing_score(&self, goal: ClearingGoal) -> (usize, usize){
+        let pieces: usize = self.inventory().values().sum();
+        let metals = self.rules().metals().saturating_sub(self.metals_taken()) as usize;
+        match goal{
+            ClearingGoal::FewestPieces => (pieces, metals),
+            ClearingGoal::FurthestMetal => (metals, pieces),
+        }
+    }
+
+    // returns false once the budget runs out
+    fn search_partial(&mut self, goal: ClearingGoal, seen: &mut HashSet<u64>, node_budget: u64,
+                      path: &mut Vec<Move>, best: &mut ((usize, usize), Vec<Move>)) -> bool{
+        if !seen.insert(self.zobrist_hash()){
+            return true;
+        }
+        if seen.len() as u64 > node_budget{
+            return false;
+        }
+
+        let score = self.clearing_score(goal);
+        if score < best.0{
+            *best = (score, path.clone());
+        }
+        if best.0 == (0, 0){
+            return true;
+        }
+
+        for m in self.legal_moves(){
+            self.apply_move(&m).expect("Legal move was rejected");
+            path.push(m);
+            let keep_going = self.search_partial(goal, seen, node_budget, path, best);
+            path.pop();
+            self.undo();
+            if !keep_going || best.0 == (0, 0){
+                return keep_going;
+            }
+        }
+        true
+    }
+
+    // every piece on the board with the reason it can't be removed right now
+    pub fn stranded_pieces(&self) -> Vec<(Cell, Stranded)>{
+        let mut pieces = Vec::new();
+        for x in 0..11{
+            for y in 0..11{
+                if let Some(p) = self.get_piece(x, y){
+                    pieces.push(Cell::new(x, y, *p));
+                }
+            }
+        }
+
+        let rules = self.rules();
+        let mut ans = Vec::new();
+        for c in pieces.iter(){
+            let partners: Vec<&Cell> = pieces.iter()
+                .filter(|o| (o.x, o.y) != (c.x, c.y) && rules.legal_pair(&c.piece, &o.piece))
+                .collect();
+            let reason = match c.piece{
+                Piece::Metal(m) if m != self.metals_taken() => Stranded::LockedMetal(self.metals_taken()),
+                _ if !rules.is_gold(&c.piece) && partners.is_empty() => Stranded::NoPartner,
+                _ if !self.is_open(c.x, c.y) => Stranded::Covered,
+                _ if !rules.is_gold(&c.piece) && partners.iter().all(|o| !self.is_open(o.x, o.y)) => Stranded::PartnersCovered,
+                // only reachable on boards that still have moves left
+                _ => continue,
+            };
+            ans.push((*c, reason));
+        }
+        ans
+    }
+
+    // how many of the neighbours of (x, y) have to be removed before it is open, or None if
+    // there is no piece there. uses the same groups of three neighbours as is_open.
+    pub fn blockers(&self, x: usize, y: usize) -> Option<usize>{
+        self.get_piece(x, y)?;
+
+        let mut neighbors = [false; 6];
+        for (i, h) in Hex::from_index(x, y).neighbors().iter().enumerate(){
+            if let Some((nx, ny)) = h.to_index(){
+                neighbors[i] = self.get_piece(nx, ny).is_some();
+            }
+        }
+        (0..6).map(|i| (0..3).filter(|k| neighbors[(i + k) % 6]).count()).min()
+    }
+
+    pub fn blocking_analysis(&self) -> BlockingAnalysis{
+        let mut gs = self.clone();
+
+        // moves from legal_moves always list the two cells in the same order, so the same
+        // pairing is always the same Move
+        let mut seen_in: HashMap<Move, u64> = HashMap::new();
+        let mut found = 0;
+        gs.for_each_solution(true, |moves| {
+            for m in moves{
+                *seen_in.entry(*m).or_insert(0) += 1;
+            }
+            found += 1;
+            found < SOLUTION_CAP
+        });
+
+        let mut pieces = Vec::new();
+        for x in 0..11{
+            for y in 0..11{
+                let piece = match self.get_piece(x, y){
+                    Some(p) => *p,
+                    None => continue,
+                };
+                let cell = Cell::new(x, y, piece);
+                let moves: Vec<&Move> = seen_in.keys().filter(|m| m.cells().contains(&cell)).collect();
+                let mut partners: Vec<Cell> = moves.iter()
+                    .flat_map(|m| m.cells())
+                    .filter(|c| *c != cell)
+                    .collect();
+                partners.sort();
+                partners.dedup();
+                pieces.push(PieceAnalysis{
+                    cell,
+                    blockers: self.blockers(x, y).expect("Piece went missing"),
+                    partners,
+                    critical: moves.len() == 1
+                });
+            }
+        }
+
+        let mut forced_pairings: Vec<Move> = seen_in.iter()
+            .filter(|(_, n)| **n == found)
+            .map(|(m, _)| *m)
+            .collect();
+        forced_pairings.sort_by_key(|m| m.cells());
+
+        BlockingAnalysis{
+            pieces,
+            forced_pairings,
+            solutions: found,
+            complete: found < SOLUTION_CAP
+        }
+    }
+
+    fn count_dead_ends(&mut self, seen: &mut HashSet<u64>, node_budget: u64, report: &mut Difficulty){
+        if !seen.insert(self.zobrist_hash()){
+            return;
+        }
+        if seen.len() as u64 > node_budget{
+            report.complete = false;
+            return;
+        }
+        report.positions += 1;
+
+        let moves = self.legal_moves();
+        if moves.is_empty() && !self.is_solved(){
+            report.dead_ends += 1;
+        }
+        for m in moves{
+            self.apply_move(&m).expect("Legal move was rejected");
+            self.count_dead_ends(seen, node_budget, report);
+            self.undo();
+            if !report.complete{
+                return;
+            }
+        }
+    }
+}
+
+pub fn test(){
+    {
+        // a single pair can't go wrong
+        let mut gs = GameState::new();
+        gs.set_piece(Some(Piece::Element(Element::Fire)), 3, 5).unwrap();
+        gs.set_piece(Some(Piece::Element(Element::Fire)), 7, 5).unwrap();
+        let d = gs.difficulty();
+        assert!(d.solvable && d.complete);
+        assert_eq!(d.solutions, 1);
+        assert_eq!(d.dead_ends, 0);
+        assert_eq!(d.positions, 2);
+        assert_eq!(d.branching, vec![1]);
+        assert_eq!(d.fatal_ratio(), 0.0);
+        assert_eq!(d.first_fatal_step, None);
+
+        // with three fires and a water, both salts have to go on elements, so pairing the
+        // salts together loses straight away
+        gs.set_piece(Some(Piece::Salt), 5, 2).unwrap();
+        gs.set_piece(Some(Piece::Salt), 5, 8).unwrap();
+        gs.set_piece(Some(Piece::Element(Element::Water)), 1, 9).unwrap();
+        gs.set_piece(Some(Piece::Element(Element::Fire)), 9, 1).unwrap();
+        let d = gs.difficulty();
+        assert!(d.solvable);
+        assert_eq!(d.solutions, 6);
+        assert_eq!(d.forced_salt, 2);
+        assert_eq!(d.salt_on_elements, 2);
+        assert!(d.dead_ends > 0);
+        assert!(d.fatal_moves > 0);
+        assert_eq!(d.first_fatal_step, Some(0));
+        assert_eq!(gs.history().len(), 0);
+        assert_eq!(gs.inventory().values().sum::<usize>(), 6);
+    }
+    {
+        let gs = GameState::example();
+        let d = gs.difficulty_with_budget(1000);
+        assert!(d.solvable);
+        assert!(!d.complete);
+        assert_eq!(d.solutions, SOLUTION_CAP);
+        assert!(d.mean_branching() > 1.0);
+        assert_eq!(gs.zobrist_hash(), GameState::example().zobrist_hash());
+    }
+    {
+        // three fires and a water: one fire and the water are left over
+        let mut gs = GameState::new();
+        gs.set_piece(Some(Piece::Element(Element::Fire)), 3, 5).unwrap();
+        gs.set_piece(Some(Piece::Element(Element::Fire)), 7, 5).unwrap();
+        gs.set_piece(Some(Piece::Element(Element::Fire)), 5, 2).unwrap();
+        gs.set_piece(Some(Piece::Element(Element::Water)), 5, 8).unwrap();
+        let partial = gs.best_partial_clearing(ClearingGoal::FewestPieces);
+        assert!(partial.complete);
+        assert_eq!(partial.moves.len(), 1);
+        assert_eq!(partial.pieces_left, 2);
+        assert_eq!(partial.stranded.len(), 2);
+        assert!(partial.stranded.iter().all(|(_, r)| *r == Stranded::NoPartner));
+        assert!(partial.stranded.iter().any(|(c, _)| c.piece == Piece::Element(Element::Water)));
+        assert_eq!(gs.history().len(), 0);
+    }
+    {
+        // vitae boxed in by quicksilver with no metals to take them
+        let mut gs = GameState::new();
+        gs.set_piece(Some(Piece::Vitae), 5, 5).unwrap();
+        gs.set_piece(Some(Piece::Mors), 0, 5).unwrap();
+        for h in Hex::ORIGIN.neighbors(){
+            let (x, y) = h.to_index().unwrap();
+            gs.set_piece(Some(Piece::Quicksilver), x, y).unwrap();
+        }
+        let partial = gs.best_partial_clearing(ClearingGoal::FewestPieces);
+        assert_eq!(partial.moves.len(), 0);
+        assert_eq!(partial.pieces_left, 8);
+        let reason = |x: usize, y: usize| partial.stranded.iter().find(|(c, _)| (c.x, c.y) == (x, y)).map(|(_, r)| *r);
+        assert_eq!(reason(5, 5), Some(Stranded::Covered));
+        assert_eq!(reason(0, 5), Some(Stranded::PartnersCovered));
+        assert_eq!(reason(6, 5), Some(Stranded::NoPartner));
+
+        // a metal waiting on one that isn't there
+        let mut gs = GameState::new();
+        gs.set_piece(Some(Piece::Metal(1)), 3, 5).unwrap();
+        gs.set_piece(Some(Piece::Quicksilver), 7, 5).unwrap();
+        gs.set_piece(Some(Piece::Salt), 5, 2).unwrap();
+        gs.set_piece(Some(Piece::Salt), 5, 8).unwrap();
+        let partial = gs.best_partial_clearing(ClearingGoal::FurthestMetal);
+        assert_eq!(partial.pieces_left, 2);
+        assert_eq!(partial.metals_taken, 0);
+        assert!(partial.stranded.contains(&(Cell::new(3, 5, Piece::Metal(1)), Stranded::LockedMetal(0))));
+        assert!(partial.stranded.contains(&(Cell::new(7, 5, Piece::Quicksilver), Stranded::PartnersCovered)));
+    }
+    {
+        // a solvable board is cleared completely
+        let gs = GameState::example();
+        let partial = gs.best_partial_clearing(ClearingGoal::FewestPieces);
+        assert_eq!(partial.pieces_left, 0);
+        assert!(partial.stranded.is_empty());
+        let mut check = GameState::example();
+        partial.moves.replay(&mut check).unwrap();
+        assert!(check.is_solved());
+    }
+    {
+        // vitae in the middle with a gap on one side, so two of the ring have to go before it
+        // opens. fire and salt can pair several ways, vitae and mors only one.
+        let mut gs = GameState::new();
+        gs.set_piece(Some(Piece::Vitae), 5, 5).unwrap();
+        let ring = [
+            Piece::Element(Element::Fire),
+            Piece::Salt,
+            Piece::Mors,
+            Piece::Salt,
+            Piece::Element(Element::Fire),
+        ];
+        for (h, p) in Hex::ORIGIN.neighbors().iter().zip(ring.iter()){
+            let (x, y) = h.to_index().unwrap();
+            gs.set_piece(Some(*p), x, y).unwrap();
+        }
+        assert_eq!(gs.blockers(5, 5), Some(2));
+        assert_eq!(gs.blockers(6, 5), Some(0));
+        assert_eq!(gs.blockers(5, 6), None);
+
+        let report = gs.blocking_analysis();
+        assert!(report.complete);
+        assert!(report.solutions > 1);
+        assert_eq!(report.forced_pairings, vec![Move::Pair(Cell::new(5, 4, Piece::Mors), Cell::new(5, 5, Piece::Vitae))]);
+        for p in report.pieces.iter(){
+            assert_eq!(p.blockers == 0, gs.is_open(p.cell.x, p.cell.y));
+            let vitae_or_mors = matches!(p.cell.piece, Piece::Vitae | Piece::Mors);
+            assert_eq!(p.critical, vitae_or_mors);
+            assert_eq!(p.partners.len() == 1, vitae_or_mors);
+        }
+    }
+    {
+        // nothing is forced on a board with no solutions
+        let mut gs = GameState::new();
+        gs.set_piece(Some(Piece::Vitae), 3, 5).unwrap();
+        gs.set_piece(Some(Piece::Salt), 7, 5).unwrap();
+        let report = gs.blocking_analysis();
+        assert_eq!(report.solutions, 0);
+        assert!(report.forced_pairings.is_empty());
+        assert!(report.pieces.iter().all(|p| !p.critical && p.partners.is_empty()));
+    }
+}
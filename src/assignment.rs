@@ -0,0 +1,144 @@
+// the hungarian algorithm: gives each row of costs its own column so that the total cost is
+// as small as possible. there can be more columns than rows, and the spare ones go unused.
+// ans[row] is the column given to row.
+pub fn min_cost_assignment(costs: &[Vec<f64>]) -> Result<Vec<usize>, String>{
+    let rows = costs.len();
+    if rows == 0{
+        return Ok(Vec::new());
+    }
+    let cols = costs[0].len();
+    if costs.iter().any(|r| r.len() != cols){
+        return Err(String::from("Cost matrix rows have different lengths"));
+    }
+    if rows > cols{
+        return Err(format!("{} rows can't each have one of {} columns", rows, cols));
+    }
+    if costs.iter().flatten().any(|c| !c.is_finite()){
+        return Err(String::from("Cost matrix has a cost that isn't a finite number"));
+    }
+
+    // everything is 1 indexed, with row 0 and column 0 standing for "nothing yet".
+    // u and v are the potentials, which keep every reduced cost at or above 0.
+    let mut u = vec![0.0; rows + 1];
+    let mut v = vec![0.0; cols + 1];
+    let mut owner = vec![0; cols + 1]; // the row each column is given to
+    let mut way = vec![0; cols + 1]; // the column before each one on the augmenting path
+
+    for row in 1..=rows{
+        owner[0] = row;
+        let mut col = 0;
+        let mut min_to = vec![f64::INFINITY; cols + 1];
+        let mut used = vec![false; cols + 1];
+        // grow a tree of tight edges from row until it reaches a free column
+        loop{
+            used[col] = true;
+            let from = owner[col];
+            let mut delta = f64::INFINITY;
+            let mut next = 0;
+            for c in 1..=cols{
+                if !used[c]{
+                    let reduced = costs[from - 1][c - 1] - u[from] - v[c];
+                    if reduced < min_to[c]{
+                        min_to[c] = reduced;
+                        way[c] = col;
+                    }
+                    if min_to[c] < delta{
+                        delta = min_to[c];
+                        next = c;
+                    }
+                }
+            }
+            for c in 0..=cols{
+                if used[c]{
+                    u[owner[c]] += delta;
+                    v[c] -= delta;
+                }else{
+                    min_to[c] -= delta;
+                }
+            }
+            col = next;
+            if owner[col] == 0{
+                break;
+            }
+        }
+        // then shift every column along the path over by one row
+        while col != 0{
+            let prev = way[col];
+            owner[col] = owner[prev];
+            col = prev;
+        }
+    }
+
+    let mut ans = vec![0; rows];
+    for c in 1..=cols{
+        if owner[c] != 0{
+            ans[owner[c] - 1] = c - 1;
+        }
+    }
+    Ok(ans)
+}
+
+fn total_cost(costs: &[Vec<f64>], assignment: &[usize]) -> f64{
+    assignment.iter().enumerate().map(|(r, c)| costs[r][*c]).sum()
+}
+
+// the cheapest assignment by trying every one, for checking against
+fn brute_force(costs: &[Vec<f64>], row: usize, taken: &mut Vec<bool>) -> f64{
+    if row == costs.len(){
+        return 0.0;
+    }
+    let mut best = f64::INFINITY;
+    for c in 0..taken.len(){
+        if !taken[c]{
+            taken[c] = true;
+            best = best.min(costs[row][c] + brute_force(costs, row + 1, taken));
+            taken[c] = false;
+        }
+    }
+    best
+}
+
+pub fn test(){
+    use super::generator::Rng;
+
+    {
+        let costs = vec![
+            vec![4.0, 1.0, 3.0],
+            vec![2.0, 0.0, 5.0],
+            vec![3.0, 2.0, 2.0],
+        ];
+        let ans = min_cost_assignment(&costs).unwrap();
+        assert_eq!(ans, vec![1, 0, 2]);
+        assert_eq!(total_cost(&costs, &ans), 5.0);
+
+        // taking the cheapest pairing first would leave row 1 with the 100
+        let costs = vec![
+            vec![0.0, 1.0],
+            vec![1.0, 100.0],
+        ];
+        assert_eq!(min_cost_assignment(&costs).unwrap(), vec![1, 0]);
+
+        assert_eq!(min_cost_assignment(&[]).unwrap(), Vec::<usize>::new());
+        assert!(min_cost_assignment(&[vec![1.0], vec![2.0]]).is_err());
+        assert!(min_cost_assignment(&[vec![1.0, 2.0], vec![2.0]]).is_err());
+        assert!(min_cost_assignment(&[vec![f64::NAN, 2.0]]).is_err());
+    }
+    {
+        // matches trying everything on small random matrices, square and wide
+        let mut rng = Rng::new(7);
+        for trial in 0..200{
+            let rows = 1 + rng.below(5);
+            let cols = rows + rng.below(3);
+            let costs: Vec<Vec<f64>> = (0..rows)
+                .map(|_| (0..cols).map(|_| rng.below(20) as f64 / 4.0).collect())
+                .collect();
+            let ans = min_cost_assignment(&costs).unwrap();
+            let mut seen = ans.clone();
+            seen.sort();
+            seen.dedup();
+            assert_eq!(seen.len(), rows, "trial {}", trial);
+            let best = brute_force(&costs, 0, &mut vec![false; cols]);
+            assert!((total_cost(&costs, &ans) - best).abs() < 1e-9, "trial {}", trial);
+        }
+    }
+}
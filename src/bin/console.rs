@@ -0,0 +1,4 @@
+// play and study boards in the terminal, without the game running
+fn main(){
+    auto_sigmar::console::run();
+}
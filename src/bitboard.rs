@@ -0,0 +1,374 @@
+use super::game::Cell;
+use super::game::Element;
+use super::game::GameState;
+use super::game::Piece;
+use super::game::Move;
+use super::game::Solution;
+use super::hex;
+use super::hex::Hex;
+use super::rules::RuleSet;
+use super::transposition;
+use super::transposition::TranspositionTable;
+
+// an alternative to GameState that keeps the 91 on-board cells as bits of a u128,
+// with one mask per kind of piece. cells are numbered in the same x-major order that
+// GameState::open_pieces walks the board in, so both versions explore moves identically.
+
+const CELLS: usize = 91;
+const KINDS: usize = 14;
+const OFF_BOARD: u8 = 255;
+
+const fn build_cell_index() -> [[u8; 11]; 11]{
+    let mut index = [[OFF_BOARD; 11]; 11];
+    let mut i = 0;
+    let mut x = 0;
+    while x < 11{
+        let mut y = 0;
+        while y < 11{
+            if Hex::from_index(x, y).on_board(){
+                index[x][y] = i;
+                i += 1;
+            }
+            y += 1;
+        }
+        x += 1;
+    }
+    index
+}
+
+const fn build_cell_coords() -> [(usize, usize); CELLS]{
+    let mut coords = [(0, 0); CELLS];
+    let mut i = 0;
+    let mut x = 0;
+    while x < 11{
+        let mut y = 0;
+        while y < 11{
+            if Hex::from_index(x, y).on_board(){
+                coords[i] = (x, y);
+                i += 1;
+            }
+            y += 1;
+        }
+        x += 1;
+    }
+    coords
+}
+
+// for each cell, one mask per group of three consecutive neighbors, in the same order as
+// GameState::is_open checks them.
+// a piece is open when one of these groups is completely empty.
+const fn build_windows() -> [[u128; 6]; CELLS]{
+    let mut windows = [[0u128; 6]; CELLS];
+    let mut i = 0;
+    while i < CELLS{
+        let (x, y) = CELL_COORDS[i];
+        let h = Hex::from_index(x, y);
+        let mut w = 0;
+        while w < 6{
+            let mut mask = 0u128;
+            let mut k = 0;
+            while k < 3{
+                let d = hex::DIRECTIONS[(w + k) % 6];
+                if let Some((nx, ny)) = Hex::new(h.q + d.q, h.r + d.r).to_index(){
+                    mask |= 1u128 << CELL_INDEX[nx][ny];
+                }
+                k += 1;
+            }
+            windows[i][w] = mask;
+            w += 1;
+        }
+        i += 1;
+    }
+    windows
+}
+
+const CELL_INDEX: [[u8; 11]; 11] = build_cell_index();
+const CELL_COORDS: [(usize, usize); CELLS] = build_cell_coords();
+const WINDOWS: [[u128; 6]; CELLS] = build_windows();
+
+const PIECES: [Piece; KINDS] = [
+    Piece::Element(Element::Water),
+    Piece::Element(Element::Fire),
+    Piece::Element(Element::Earth),
+    Piece::Element(Element::Air),
+    Piece::Salt,
+    Piece::Quicksilver,
+    Piece::Vitae,
+    Piece::Mors,
+    Piece::Metal(0),
+    Piece::Metal(1),
+    Piece::Metal(2),
+    Piece::Metal(3),
+    Piece::Metal(4),
+    Piece::Metal(5),
+];
+
+fn cell(x: usize, y: usize) -> Option<usize>{
+    if x >= 11 || y >= 11 || CELL_INDEX[x][y] == OFF_BOARD{
+        None
+    }else{
+        Some(CELL_INDEX[x][y] as usize)
+    }
+}
+
+pub struct BitGameState{
+    kinds: [u128; KINDS],
+    occupied: u128,
+    metals_taken: u8,
+    rules: RuleSet
+}
+
+impl BitGameState{
+    pub fn new() -> BitGameState{
+        BitGameState::with_rules(RuleSet::standard())
+    }
+
+    pub fn with_rules(rules: RuleSet) -> BitGameState{
+        BitGameState{
+            kinds: [0; KINDS],
+            occupied: 0,
+            metals_taken: 0u8,
+            rules
+        }
+    }
+
+    pub fn from_game_state(gs: &GameState) -> BitGameState{
+        let mut bgs = BitGameState::with_rules(gs.rules().clone());
+        bgs.metals_taken = gs.metals_taken();
+        for (x, y) in CELL_COORDS.iter(){
+            if let Some(p) = gs.get_piece(*x, *y){
+                bgs.set_piece(Some(*p), *x, *y).expect("Failed to place piece.");
+            }
+        }
+
+        bgs
+    }
+
+    pub fn get_piece(&self, x: usize, y: usize) -> Option<Piece>{
+        let i = cell(x, y)?;
+        if self.occupied & (1 << i) == 0{
+            None
+        }else{
+            (0..KINDS).find(|k| self.kinds[*k] & (1 << i) != 0).map(|k| PIECES[k])
+        }
+    }
+
+    pub fn set_piece(&mut self, piece: Option<Piece>, x: usize, y: usize) -> Result<Option<Piece>, String>{
+        let i = match cell(x, y){
+            Some(i) => i,
+            None => return Err(String::from("Attempted to place piece off the board")),
+        };
+        if piece.is_some() && !self.rules.on_board(x, y){
+            return Err(String::from("Attempted to place piece off the board"));
+        }
+        if let Some(p) = piece{
+            if p.index() >= KINDS{
+                return Err(format!("{:?} does not fit in a bitboard", p));
+            }
+        }
+
+        let bit = 1u128 << i;
+        for k in 0..KINDS{
+            self.kinds[k] &= !bit;
+        }
+        self.occupied &= !bit;
+        if let Some(p) = piece{
+            self.kinds[p.index()] |= bit;
+            self.occupied |= bit;
+        }
+
+        Ok(piece)
+    }
+
+    // metals other than the next one in the chain can never be open
+    fn locked_metals(&self) -> u128{
+        let mut locked = 0;
+        for m in 0..self.rules.metals(){
+            if m != self.metals_taken{
+                locked |= self.kinds[Piece::Metal(m).index()];
+            }
+        }
+        locked
+    }
+
+    fn is_open_index(&self, i: usize) -> bool{
+        WINDOWS[i].iter().any(|w| self.occupied & w == 0)
+    }
+
+    pub fn open_mask(&self) -> u128{
+        let mut candidates = self.occupied & !self.locked_metals();
+        let mut open = 0;
+        while candidates != 0{
+            let i = candidates.trailing_zeros() as usize;
+            candidates &= candidates - 1;
+            if self.is_open_index(i){
+                open |= 1 << i;
+            }
+        }
+        open
+    }
+
+    pub fn open_pieces(&self) -> Vec<(usize, usize)>{
+        let mut open = self.open_mask();
+        let mut ans = Vec::new();
+        while open != 0{
+            let i = open.trailing_zeros() as usize;
+            open &= open - 1;
+            ans.push(CELL_COORDS[i]);
+        }
+        ans
+    }
+
+    pub fn is_open(&self, x: usize, y: usize) -> bool{
+        match cell(x, y){
+            Some(i) => {
+                self.occupied & (1 << i) != 0
+                    && self.locked_metals() & (1 << i) == 0
+                    && self.is_open_index(i)
+            },
+            None => false,
+        }
+    }
+
+    pub fn is_solved(&self) -> bool{
+        self.occupied == 0
+    }
+
+    pub fn zobrist_hash(&self) -> u64{
+        let mut hash = transposition::metals_key(self.metals_taken);
+        let mut occupied = self.occupied;
+        while occupied != 0{
+            let i = occupied.trailing_zeros() as usize;
+            occupied &= occupied - 1;
+            let (x, y) = CELL_COORDS[i];
+            hash ^= transposition::piece_key(&self.get_piece(x, y).expect("Occupied cell was empty"), x, y);
+        }
+        hash
+    }
+
+    pub fn solve(&mut self) -> Option<Solution>{
+        let mut table = TranspositionTable::default();
+        self.solve_with_table(&mut table)
+    }
+
+    pub fn solve_with_table(&mut self, table: &mut TranspositionTable) -> Option<Solution>{
+        let hash = self.zobrist_hash();
+        self.solve_hashed(hash, table).map(|mut moves| {
+            moves.reverse();
+            Solution::from(moves)
+        })
+    }
+
+    // the moves come back in reverse order, like GameState::solve_hashed
+    fn solve_hashed(&mut self, hash: u64, table: &mut TranspositionTable) -> Option<Vec<Move>>{
+        if self.is_solved(){
+            return Some(Vec::new());
+        }else if table.is_dead(hash){
+            return None;
+        }
+
+        let mut open = Vec::new();
+        let mut mask = self.open_mask();
+        while mask != 0{
+            let i = mask.trailing_zeros() as usize;
+            mask &= mask - 1;
+            let kind = (0..KINDS).find(|k| self.kinds[*k] & (1 << i) != 0)
+                .expect("Open piece was None");
+            open.push((i, kind));
+        }
+
+        for (n, (i, ikind)) in open.iter().enumerate(){
+            let gold = self.rules.is_gold(&PIECES[*ikind]);
+            let partners = if gold{ &open[n..(n+1)] }else{ &open[(n+1)..] };
+            for (j, jkind) in partners{
+                if !gold && !self.rules.legal_pair(&PIECES[*ikind], &PIECES[*jkind]){
+                    continue;
+                }
+                let (ipos, jpos) = (CELL_COORDS[*i], CELL_COORDS[*j]);
+                let mut newhash = hash ^ transposition::piece_key(&PIECES[*ikind], ipos.0, ipos.1);
+                if !gold{
+                    newhash ^= transposition::piece_key(&PIECES[*jkind], jpos.0, jpos.1);
+                }
+                let metalchange = match (PIECES[*ikind], PIECES[*jkind]){
+                    (_, Piece::Metal(_)) => 1,
+                    (Piece::Metal(_), _) => 1,
+                    _other => 0
+                };
+                if metalchange != 0{
+                    newhash ^= transposition::metals_key(self.metals_taken)
+                        ^ transposition::metals_key(self.metals_taken + metalchange);
+                }
+
+                let bits = (1u128 << i) | (1u128 << j);
+                self.kinds[*ikind] &= !bits;
+                self.kinds[*jkind] &= !bits;
+                self.occupied &= !bits;
+                self.metals_taken += metalchange;
+                let rec = self.solve_hashed(newhash, table);
+                self.metals_taken -= metalchange;
+                self.occupied |= bits;
+                self.kinds[*ikind] |= 1 << i;
+                self.kinds[*jkind] |= 1 << j;
+
+                if let Some(mut v) = rec{
+                    let icell = Cell::new(ipos.0, ipos.1, PIECES[*ikind]);
+                    if gold{
+                        v.push(Move::Single(icell));
+                    }else{
+                        v.push(Move::Pair(icell, Cell::new(jpos.0, jpos.1, PIECES[*jkind])));
+                    }
+                    return Some(v);
+                }
+            }
+        }
+
+        table.mark_dead(hash);
+        None
+    }
+}
+
+impl Default for BitGameState{
+    fn default() -> BitGameState{
+        BitGameState::new()
+    }
+}
+
+// checks the bitboard against the array version
+pub fn test(){
+    {
+        let mut bgs = BitGameState::new();
+        assert!(bgs.set_piece(Some(Piece::Salt), 0, 0).is_err());
+        assert!(bgs.set_piece(Some(Piece::Salt), 11, 5).is_err());
+        assert!(bgs.set_piece(Some(Piece::Salt), 5, 5).is_ok());
+        assert_eq!(bgs.get_piece(5, 5), Some(Piece::Salt));
+        assert!(bgs.set_piece(None, 5, 5).is_ok());
+        assert_eq!(bgs.get_piece(5, 5), None);
+    }
+    {
+        let mut gs = GameState::example();
+        let mut bgs = BitGameState::from_game_state(&gs);
+
+        // walk down the solution, comparing every cell at every step
+        let solution = gs.solve().expect("Example should be solvable");
+        assert_eq!(bgs.solve(), Some(solution.clone()));
+        for action in &solution{
+            for x in 0..11{
+                for y in 0..11{
+                    if GameState::on_board(x, y){
+                        assert_eq!(gs.get_piece(x, y).copied(), bgs.get_piece(x, y));
+                        assert_eq!(gs.is_open(x, y), bgs.is_open(x, y));
+                    }
+                }
+            }
+            assert_eq!(gs.open_pieces(), bgs.open_pieces());
+            assert_eq!(gs.is_solved(), bgs.is_solved());
+            assert_eq!(gs.zobrist_hash(), bgs.zobrist_hash());
+
+            for c in action.cells(){
+                gs.set_piece(None, c.x, c.y).unwrap();
+                bgs.set_piece(None, c.x, c.y).unwrap();
+            }
+        }
+        assert!(gs.is_solved() && bgs.is_solved());
+    }
+}
@@ -0,0 +1,225 @@
+use super::game::Element;
+use super::game::GameState;
+use super::game::Piece;
+use super::rules::RuleSet;
+
+// a short printable code for a board, for pasting into chat and bug reports.
+// it is one nibble for metals_taken, then one nibble per on-board cell in x-major order
+// (0 for empty, Piece::index + 1 otherwise), then a 16 bit fletcher checksum of those
+// 46 bytes, all written out in url-safe base64 with no padding.
+
+const CELLS: usize = 91;
+const DATA_BYTES: usize = CELLS.div_ceil(2);
+const CODE_BYTES: usize = DATA_BYTES + 2;
+pub const CODE_LENGTH: usize = CODE_BYTES * 4 / 3;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn cells() -> impl Iterator<Item = (usize, usize)>{
+    (0..11).flat_map(|x| (0..11).map(move |y| (x, y))).filter(|(x, y)| GameState::on_board(*x, *y))
+}
+
+fn checksum(data: &[u8]) -> u16{
+    let (mut a, mut b) = (0u16, 0u16);
+    for byte in data{
+        a = (a + *byte as u16) % 255;
+        b = (b + a) % 255;
+    }
+    (b << 8) | a
+}
+
+fn to_base64(bytes: &[u8]) -> String{
+    let mut ans = String::new();
+    for chunk in bytes.chunks(3){
+        let n = (chunk[0] as u32) << 16 | (chunk[1] as u32) << 8 | chunk[2] as u32;
+        for shift in [18, 12, 6, 0]{
+            ans.push(ALPHABET[(n >> shift) as usize & 63] as char);
+        }
+    }
+    ans
+}
+
+fn from_base64(code: &str) -> Result<Vec<u8>, String>{
+    let mut sextets = Vec::new();
+    for (i, c) in code.chars().enumerate(){
+        match ALPHABET.iter().position(|a| *a as char == c){
+            Some(v) => sextets.push(v as u32),
+            None => return Err(format!("Invalid character '{}' at position {}", c, i + 1)),
+        }
+    }
+
+    let mut bytes = Vec::new();
+    for chunk in sextets.chunks(4){
+        let n = chunk[0] << 18 | chunk[1] << 12 | chunk[2] << 6 | chunk[3];
+        bytes.extend_from_slice(&[(n >> 16) as u8, (n >> 8) as u8, n as u8]);
+    }
+    Ok(bytes)
+}
+
+// a board that could have come from a full one under the rules by playing legal moves: no
+// extra pieces, and every metal before metals_taken gone with every metal after it still
+// there. under the real game's pairs, also a quicksilver used up for each base metal taken,
+// a salt used up for each element with an odd number gone, and as many vitae gone as mors
+fn check_inventory(gs: &GameState) -> Result<(), String>{
+    let rules = gs.rules();
+    let inventory = gs.inventory();
+    let count = |p: &Piece| *inventory.get(p).unwrap_or(&0);
+
+    for (p, n) in inventory.iter(){
+        if *n > rules.count(p){
+            return Err(format!("Board has {} of {:?}, but there are only {}", n, p, rules.count(p)));
+        }
+    }
+
+    let taken = gs.metals_taken();
+    if taken > rules.metals(){
+        return Err(format!("{} metals taken, but there are only {}", taken, rules.metals()));
+    }
+    for m in 0..rules.metals(){
+        let expected = if m < taken{ 0 }else{ 1 };
+        if count(&Piece::Metal(m)) != expected{
+            return Err(format!("Metal {} should {}be on the board with {} metals taken",
+                m, if expected == 0{ "not " }else{ "" }, taken));
+        }
+    }
+
+    // the rest counts pieces off in the real game's pairs, which says nothing about other pairs
+    if !rules.has_standard_pairs(){
+        return Ok(());
+    }
+    let quicksilver = rules.count(&Piece::Quicksilver).saturating_sub(taken.min(rules.gold()) as usize);
+    if count(&Piece::Quicksilver) != quicksilver{
+        return Err(format!("Board has {} quicksilver, but should have {} with {} metals taken",
+            count(&Piece::Quicksilver), quicksilver, taken));
+    }
+
+    // elements go in pairs or one at a time with a salt, and the rest of the salt goes in pairs
+    let gone = |p: Piece| rules.count(&p) - count(&p);
+    let salt_gone = gone(Piece::Salt);
+    let odd_elements: Vec<Element> = [Element::Water, Element::Fire, Element::Earth, Element::Air].iter()
+        .filter(|e| gone(Piece::Element(**e)) % 2 == 1)
+        .copied()
+        .collect();
+    if odd_elements.len() > salt_gone || (salt_gone - odd_elements.len()) % 2 == 1{
+        return Err(format!("{} salt gone can't account for an odd number of {:?} gone", salt_gone, odd_elements));
+    }
+
+    if gone(Piece::Vitae) != gone(Piece::Mors){
+        return Err(format!("Board has {} vitae gone but {} mors", gone(Piece::Vitae), gone(Piece::Mors)));
+    }
+
+    Ok(())
+}
+
+impl GameState{
+    pub fn to_code(&self) -> String{
+        let mut nibbles = vec![self.metals_taken()];
+        for (x, y) in cells(){
+            nibbles.push(match self.get_piece(x, y){
+                Some(p) => p.index() as u8 + 1,
+                None => 0,
+            });
+        }
+
+        let mut bytes: Vec<u8> = nibbles.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect();
+        let sum = checksum(&bytes);
+        bytes.extend_from_slice(&sum.to_be_bytes());
+        to_base64(&bytes)
+    }
+
+    pub fn from_code(code: &str) -> Result<GameState, String>{
+        GameState::from_code_with_rules(code, RuleSet::standard())
+    }
+
+    // the rules aren't part of the code, so boards for other rules have to say which
+    pub fn from_code_with_rules(code: &str, rules: RuleSet) -> Result<GameState, String>{
+        let code = code.trim();
+        if code.chars().count() != CODE_LENGTH{
+            return Err(format!("Board code should be {} characters, not {}", CODE_LENGTH, code.chars().count()));
+        }
+        let bytes = from_base64(code)?;
+        let (data, sum) = bytes.split_at(DATA_BYTES);
+        if checksum(data).to_be_bytes() != sum{
+            return Err(String::from("Board code checksum does not match, it may have been mistyped"));
+        }
+
+        let nibbles: Vec<u8> = data.iter().flat_map(|b| [b >> 4, b & 15]).collect();
+        let mut gs = GameState::with_rules(rules);
+        gs.set_metals_taken(nibbles[0]);
+        for ((x, y), n) in cells().zip(nibbles[1..].iter()){
+            if *n != 0{
+                let piece = Piece::from_index(*n as usize - 1)
+                    .ok_or_else(|| format!("Invalid piece {} at ({}, {})", n, x, y))?;
+                gs.set_piece(Some(piece), x, y)?;
+            }
+        }
+
+        check_inventory(&gs)?;
+        Ok(gs)
+    }
+}
+
+pub fn test(){
+    {
+        let mut gs = GameState::example();
+        let code = gs.to_code();
+        assert_eq!(code.len(), CODE_LENGTH);
+        assert!(code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        let decoded = GameState::from_code(&code).unwrap();
+        assert_eq!(decoded.to_string(), gs.to_string());
+        assert_eq!(GameState::from_code(&format!(" {}\n", code)).unwrap().zobrist_hash(), gs.zobrist_hash());
+
+        // part way through, with some metals gone
+        let solution = gs.solve().expect("Example should be solvable");
+        for action in solution.iter().take(18){
+            gs.apply_move(action).unwrap();
+        }
+        assert_eq!(gs.metals_taken(), 4);
+        let decoded = GameState::from_code(&gs.to_code()).unwrap();
+        assert_eq!(decoded.metals_taken(), 4);
+        assert_eq!(decoded.zobrist_hash(), gs.zobrist_hash());
+
+        let empty = GameState::new();
+        assert!(GameState::from_code(&empty.to_code()).is_err());
+    }
+    {
+        let code = GameState::example().to_code();
+        assert!(GameState::from_code(&code[1..]).is_err());
+        assert!(GameState::from_code(&format!("{}A", code)).is_err());
+        assert!(GameState::from_code(&code.replacen(&code[..1], "*", 1)).is_err());
+
+        // changing any one character is caught
+        for i in 0..code.len(){
+            let c = code.as_bytes()[i];
+            let other = if c == b'A'{ 'B' }else{ 'A' };
+            let mut mistyped = String::from(&code[..i]);
+            mistyped.push(other);
+            mistyped.push_str(&code[(i+1)..]);
+            assert!(GameState::from_code(&mistyped).is_err());
+        }
+
+        // a well formed code for a board with too many fires
+        let mut gs = GameState::example();
+        gs.set_piece(Some(Piece::Element(Element::Fire)), 5, 6).unwrap();
+        assert!(GameState::from_code(&gs.to_code()).err().unwrap().contains("only"));
+        // and one with a metal missing
+        let mut gs = GameState::example();
+        gs.set_piece(None, 5, 5).unwrap();
+        assert!(GameState::from_code(&gs.to_code()).is_err());
+
+        // a vitae gone without a mors
+        let mut gs = GameState::example();
+        let (x, y) = cells().find(|(x, y)| gs.get_piece(*x, *y) == Some(&Piece::Vitae)).unwrap();
+        gs.set_piece(None, x, y).unwrap();
+        assert!(GameState::from_code(&gs.to_code()).err().unwrap().contains("mors"));
+        // and a fire gone with every salt still there
+        let mut gs = GameState::example();
+        let (x, y) = cells().find(|(x, y)| gs.get_piece(*x, *y) == Some(&Piece::Element(Element::Fire))).unwrap();
+        gs.set_piece(None, x, y).unwrap();
+        assert!(GameState::from_code(&gs.to_code()).err().unwrap().contains("salt"));
+        // but a fire gone with a salt is fine
+        let (x, y) = cells().find(|(x, y)| gs.get_piece(*x, *y) == Some(&Piece::Salt)).unwrap();
+        gs.set_piece(None, x, y).unwrap();
+        assert!(GameState::from_code(&gs.to_code()).is_ok());
+    }
+}
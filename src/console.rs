@@ -0,0 +1,257 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+
+use image;
+
+use super::game::Cell;
+use super::game::Element;
+use super::game::GameState;
+use super::game::Move;
+use super::game::Piece;
+use super::game_reader;
+use super::generator;
+use super::locate;
+use super::render;
+use super::rules::RuleSet;
+
+const HELP: &str = "\
+commands:
+  load text <file>     read a board written like GameState's Display
+  load image <file>    read a board from a screenshot
+  load code <code>     read a board code
+  load example         the board from Game1.png
+  generate <seed>      a random solvable board
+  open                 list the pieces that can be taken
+  move <x> <y> [<x> <y>]
+                       take a pair, or gold on its own
+  undo, redo           step back and forward through the moves made
+  hint                 mark the next move of a solution from here
+  solve                a full solution from here
+  check                whether the board can still be cleared
+  code                 the board code of the current board
+  help                 this list
+  quit";
+
+// a board being played in the terminal. every command is one line, and the board is
+// drawn again after each one.
+pub struct Console{
+    gs: GameState,
+    hint: Option<Move> // marked on the board until the next command
+}
+
+impl Console{
+    pub fn new() -> Console{
+        Console{gs: GameState::example(), hint: None}
+    }
+
+    pub fn state(&self) -> &GameState{
+        &self.gs
+    }
+
+    pub fn draw(&self) -> String{
+        render::render(&self.gs, self.hint.as_ref())
+    }
+
+    // runs one command, giving back what to tell the player
+    pub fn execute(&mut self, line: &str) -> Result<String, String>{
+        self.hint = None;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice(){
+            [] => Ok(String::new()),
+            ["help"] => Ok(String::from(HELP)),
+            ["load", "text", path] => {
+                let text = fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+                self.gs = GameState::parse_with_rules(&text, self.gs.rules().clone())
+                    .map_err(|e| format!("{}: {}", path, e))?;
+                Ok(format!("Loaded {}", path))
+            },
+            ["load", "image", path] => {
+                let im = image::open(path).map_err(|e| format!("Couldn't open {}: {}", path, e))?;
+                let geometry = locate::locate_board(&im).map_err(|e| format!("{}: {}", path, e))?;
+                let reading = game_reader::read_board(&im, &geometry, &RuleSet::standard())?;
+                let uncertain = reading.uncertain(game_reader::UNCERTAIN_MARGIN);
+                self.gs = reading.state;
+                let mut message = format!("Read {}", path);
+                if !uncertain.is_empty(){
+                    message += ", but these cells were hard to tell apart:";
+                    for c in uncertain{
+                        message += &format!("\n  {}", c);
+                    }
+                }
+                Ok(message)
+            },
+            ["load", "code", code] => {
+                self.gs = GameState::from_code(code)?;
+                Ok(String::from("Loaded board code"))
+            },
+            ["load", "example"] => {
+                self.gs = GameState::example();
+                Ok(String::from("Loaded the example"))
+            },
+            ["generate", seed] => {
+                let seed = seed.parse().map_err(|_| format!("'{}' is not a seed", seed))?;
+                self.gs = generator::generate(seed);
+                Ok(format!("Generated board {}", seed))
+            },
+            ["open"] => {
+                let cells: Vec<String> = self.gs.open_pieces().iter()
+                    .map(|(x, y)| self.cell(*x, *y).map(|c| c.to_string()))
+                    .collect::<Result<_, _>>()?;
+                Ok(format!("Open: {}", cells.join(" ")))
+            },
+            ["move", coords @ ..] => {
+                let action = self.parse_move(coords)?;
+                self.gs.apply_move(&action)?;
+                Ok(format!("Took {}", action))
+            },
+            ["undo"] => match self.gs.undo(){
+                Some(action) => Ok(format!("Put back {}", action)),
+                None => Err(String::from("Nothing to undo")),
+            },
+            ["redo"] => match self.gs.redo(){
+                Some(action) => Ok(format!("Took {}", action)),
+                None => Err(String::from("Nothing to redo")),
+            },
+            ["hint"] => match self.gs.clone().solve(){
+                Some(s) if !s.is_empty() => {
+                    self.hint = Some(s.moves()[0]);
+                    Ok(format!("Try {}", s.moves()[0]))
+                },
+                Some(_) => Ok(String::from("The board is already clear")),
+                None => Err(String::from("There is no way to clear the board from here")),
+            },
+            ["solve"] => match self.gs.clone().solve(){
+                Some(s) => Ok(s.to_string()),
+                None => Err(String::from("There is no way to clear the board from here")),
+            },
+            ["check"] => {
+                if self.gs.is_solved(){
+                    Ok(String::from("The board is clear"))
+                }else if !self.gs.could_be_solvable(){
+                    Ok(String::from("Not solvable: the pieces left can't all be paired"))
+                }else if self.gs.clone().solve().is_none(){
+                    Ok(String::from("Not solvable from here"))
+                }else{
+                    Ok(String::from("Still solvable"))
+                }
+            },
+            ["code"] => Ok(self.gs.to_code()),
+            _other => Err(format!("Unknown command '{}', try help", line.trim())),
+        }
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Result<Cell, String>{
+        match self.gs.get_piece(x, y){
+            Some(p) => Ok(Cell::new(x, y, *p)),
+            None => Err(format!("There is no piece at ({}, {})", x, y)),
+        }
+    }
+
+    fn parse_move(&self, coords: &[&str]) -> Result<Move, String>{
+        let numbers: Vec<usize> = coords.iter()
+            .map(|c| c.parse().map_err(|_| format!("'{}' is not a coordinate", c)))
+            .collect::<Result<_, _>>()?;
+        match numbers.as_slice(){
+            [x, y] => Ok(Move::Single(self.cell(*x, *y)?)),
+            [x1, y1, x2, y2] => Ok(Move::Pair(self.cell(*x1, *y1)?, self.cell(*x2, *y2)?)),
+            _other => Err(String::from("A move needs one or two cells, like: move 0 5 10 0")),
+        }
+    }
+}
+
+impl Default for Console{
+    fn default() -> Console{
+        Console::new()
+    }
+}
+
+// reads commands from stdin until quit or the end of input
+pub fn run(){
+    let mut console = Console::new();
+    println!("{}", HELP);
+    print!("{}", console.draw());
+
+    let stdin = io::stdin();
+    loop{
+        print!("> ");
+        io::stdout().flush().expect("Couldn't write to stdout");
+        let mut line = String::new();
+        match stdin.read_line(&mut line){
+            Ok(0) => break,
+            Ok(_) => (),
+            Err(e) => {
+                println!("Couldn't read input: {}", e);
+                break;
+            }
+        }
+        if matches!(line.trim(), "quit" | "exit"){
+            break;
+        }
+
+        match console.execute(&line){
+            Ok(message) => println!("{}", message),
+            Err(message) => println!("Error: {}", message),
+        }
+        print!("{}", console.draw());
+    }
+}
+
+pub fn test(){
+    {
+        let mut console = Console::new();
+        assert!(console.execute("bogus").is_err());
+        assert!(console.execute("").is_ok());
+        assert!(console.execute("open").unwrap().contains("A(0, 5)"));
+
+        // (5, 9) isn't open yet
+        assert!(console.execute("move 5 9 9 1").is_err());
+        assert!(console.execute("move 0 5").is_err());
+        assert!(console.execute("move 0 5 10").is_err());
+        assert!(console.execute("move 0 5 10 0").is_ok());
+        assert_eq!(console.state().history().len(), 1);
+        assert!(console.execute("undo").is_ok());
+        assert!(console.execute("undo").is_err());
+        assert!(console.execute("redo").is_ok());
+        assert_eq!(console.state().get_piece(0, 5), None);
+
+        assert!(console.execute("hint").unwrap().starts_with("Try "));
+        assert!(console.draw().contains("\x1b[7m"));
+        assert_eq!(console.execute("check").unwrap(), "Still solvable");
+        assert!(!console.draw().contains("\x1b[7m"));
+        assert!(console.execute("solve").unwrap().lines().count() > 10);
+        // asking for help doesn't play anything
+        assert_eq!(console.state().history().len(), 1);
+    }
+    {
+        let mut console = Console::new();
+        let code = console.execute("code").unwrap();
+        console.execute("generate 3").unwrap();
+        assert_eq!(console.state().zobrist_hash(), generator::generate(3).zobrist_hash());
+        console.execute(&format!("load code {}", code)).unwrap();
+        assert_eq!(console.state().zobrist_hash(), GameState::example().zobrist_hash());
+        assert!(console.execute("load code nonsense").is_err());
+        assert!(console.execute("load text does/not/exist.txt").is_err());
+        assert!(console.execute("generate x").is_err());
+
+        // screenshots say which cells they aren't sure of, if any
+        let read = console.execute("load image images/Game1.png").unwrap();
+        assert_eq!(read, "Read images/Game1.png");
+        assert_eq!(console.state().zobrist_hash(), GameState::example().zobrist_hash());
+        let read = console.execute("load image images/Game2.png").unwrap();
+        assert!(read.lines().next().unwrap().ends_with("hard to tell apart:"));
+        assert!(read.lines().nth(1).unwrap().contains(": A"));
+
+        // a fire and a water can never be cleared
+        let mut gs = GameState::new();
+        gs.set_piece(Some(Piece::Element(Element::Fire)), 3, 5).unwrap();
+        gs.set_piece(Some(Piece::Element(Element::Water)), 7, 5).unwrap();
+        let path = std::env::temp_dir().join("auto_sigmar_console_test.txt");
+        fs::write(&path, gs.to_string()).unwrap();
+        console.execute(&format!("load text {}", path.display())).unwrap();
+        assert_eq!(console.state().zobrist_hash(), gs.zobrist_hash());
+        assert!(console.execute("check").unwrap().starts_with("Not solvable"));
+        assert!(console.execute("hint").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,5 +1,17 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use super::hex::Hex;
+use super::hex::Symmetry;
+use super::rules::RuleSet;
+use super::transposition;
+use super::transposition::CountTable;
+use super::transposition::TranspositionTable;
+
 // structs and enums to represent game state
-#[derive(PartialEq, Copy, Clone, Debug, Eq, Hash)]
+#[derive(PartialEq, Copy, Clone, Debug, Eq, Hash, PartialOrd, Ord)]
 pub enum Element{
     Water,
     Fire,
@@ -7,28 +19,31 @@ pub enum Element{
     Air
 }
 
-#[derive(PartialEq, Copy, Clone, Debug, Eq, Hash)]
+#[derive(PartialEq, Copy, Clone, Debug, Eq, Hash, PartialOrd, Ord)]
 pub enum Piece{
     Element(Element),
     Salt,
-    Metal(u8), // the metals, with lead being 0 and gold being 7 (6?)
+    Metal(u8), // the metals, with lead being 0 and gold being 5
     Quicksilver,
     Vitae,
     Mors
 }
 
+// gold is the last metal, and is removed on its own rather than with quicksilver
+pub const GOLD: u8 = 5;
+
 impl Piece{
     // metals can always be matched according to this, but they will not be considered
     // "unlocked" until other metals have been cleared.
-    fn legal_pair(&self, other: &Piece) -> bool{
+    pub fn legal_pair(&self, other: &Piece) -> bool{
         match (self, other){
             (Piece::Element(a), Piece::Element(b)) => a == b,
             (Piece::Element(_), Piece::Salt) => true,
             (Piece::Salt, Piece::Element(_)) => true,
             (Piece::Salt, Piece::Salt) => true,
 
-            (Piece::Metal(_), Piece::Quicksilver) => true,
-            (Piece::Quicksilver, Piece::Metal(_)) => true,
+            (Piece::Metal(m), Piece::Quicksilver) => *m != GOLD,
+            (Piece::Quicksilver, Piece::Metal(m)) => *m != GOLD,
 
             (Piece::Vitae, Piece::Mors) => true,
             (Piece::Mors, Piece::Vitae) => true,
@@ -37,7 +52,35 @@ impl Piece{
         }
     }
 
-    fn tochar(&self) -> String{
+    // a small dense index for each kind of piece, metals last
+    pub fn index(&self) -> usize{
+        match self{
+            Piece::Element(e) => *e as usize,
+            Piece::Salt => 4,
+            Piece::Quicksilver => 5,
+            Piece::Vitae => 6,
+            Piece::Mors => 7,
+            Piece::Metal(m) => 8 + (*m as usize),
+        }
+    }
+
+    // the inverse of index
+    pub fn from_index(i: usize) -> Option<Piece>{
+        match i{
+            0 => Some(Piece::Element(Element::Water)),
+            1 => Some(Piece::Element(Element::Fire)),
+            2 => Some(Piece::Element(Element::Earth)),
+            3 => Some(Piece::Element(Element::Air)),
+            4 => Some(Piece::Salt),
+            5 => Some(Piece::Quicksilver),
+            6 => Some(Piece::Vitae),
+            7 => Some(Piece::Mors),
+            m if m <= 8 + GOLD as usize => Some(Piece::Metal((m - 8) as u8)),
+            _other => None,
+        }
+    }
+
+    pub fn tochar(&self) -> String{
         match self{
             Piece::Element(Element::Fire) => String::from("F"),
             Piece::Element(Element::Water) => String::from("W"),
@@ -50,34 +93,229 @@ impl Piece{
             Piece::Mors => String::from("M"),
         }
     }
+
+    // the inverse of tochar
+    pub fn from_char(c: char) -> Option<Piece>{
+        match c{
+            'F' => Some(Piece::Element(Element::Fire)),
+            'W' => Some(Piece::Element(Element::Water)),
+            'E' => Some(Piece::Element(Element::Earth)),
+            'A' => Some(Piece::Element(Element::Air)),
+            'S' => Some(Piece::Salt),
+            'Q' => Some(Piece::Quicksilver),
+            'V' => Some(Piece::Vitae),
+            'M' => Some(Piece::Mors),
+            _other => c.to_digit(10).filter(|d| *d <= GOLD as u32).map(|d| Piece::Metal(d as u8)),
+        }
+    }
+}
+
+// a position on the board along with the piece sitting there
+#[derive(PartialEq, Copy, Clone, Debug, Eq, Hash, PartialOrd, Ord)]
+pub struct Cell{
+    pub x: usize,
+    pub y: usize,
+    pub piece: Piece
+}
+
+impl Cell{
+    pub fn new(x: usize, y: usize, piece: Piece) -> Cell{
+        Cell{x, y, piece}
+    }
+
+    pub fn hex(&self) -> Hex{
+        Hex::from_index(self.x, self.y)
+    }
+
+    pub fn transformed(&self, sym: &Symmetry) -> Cell{
+        let (x, y) = sym.apply(self.hex()).to_index().expect("Symmetry moved a cell off the board");
+        Cell{x, y, piece: self.piece}
+    }
+}
+
+impl fmt::Display for Cell{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        write!(f, "{}({}, {})", self.piece.tochar(), self.x, self.y)
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Eq, Hash, PartialOrd, Ord)]
+pub enum Move{
+    Pair(Cell, Cell),
+    Single(Cell) // only gold is removed on its own
+}
+
+impl Move{
+    pub fn cells(&self) -> Vec<Cell>{
+        match self{
+            Move::Pair(a, b) => vec![*a, *b],
+            Move::Single(a) => vec![*a],
+        }
+    }
+
+    pub fn shares_cell(&self, other: &Move) -> bool{
+        let theirs = other.cells();
+        self.cells().iter().any(|a| theirs.iter().any(|b| (a.x, a.y) == (b.x, b.y)))
+    }
+
+    pub fn transformed(&self, sym: &Symmetry) -> Move{
+        match self{
+            Move::Pair(a, b) => Move::Pair(a.transformed(sym), b.transformed(sym)),
+            Move::Single(a) => Move::Single(a.transformed(sym)),
+        }
+    }
+
+    fn takes_metal(&self) -> bool{
+        self.cells().iter().any(|c| matches!(c.piece, Piece::Metal(_)))
+    }
+}
+
+impl fmt::Display for Move{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        match self{
+            Move::Pair(a, b) => write!(f, "{} + {}", a, b),
+            Move::Single(a) => write!(f, "{}", a),
+        }
+    }
+}
+
+// a sequence of moves that clears a board, in the order they should be played
+#[derive(PartialEq, Clone, Debug, Eq, Hash)]
+pub struct Solution{
+    moves: Vec<Move>
+}
+
+impl Solution{
+    pub fn moves(&self) -> &[Move]{
+        &self.moves
+    }
+
+    pub fn len(&self) -> usize{
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.moves.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Move>{
+        self.moves.iter()
+    }
+
+    // the same solution for a board moved by sym
+    pub fn transformed(&self, sym: &Symmetry) -> Solution{
+        Solution{moves: self.moves.iter().map(|m| m.transformed(sym)).collect()}
+    }
+
+    // plays the moves on gs with apply_move, stopping at the first illegal one
+    pub fn replay(&self, gs: &mut GameState) -> Result<(), String>{
+        for (i, m) in self.moves.iter().enumerate(){
+            gs.apply_move(m).map_err(|e| format!("Move {} ({}): {}", i+1, m, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Vec<Move>> for Solution{
+    fn from(moves: Vec<Move>) -> Solution{
+        Solution{moves}
+    }
+}
+
+impl<'a> IntoIterator for &'a Solution{
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter{
+        self.moves.iter()
+    }
+}
+
+impl fmt::Display for Solution{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        for (i, m) in self.moves.iter().enumerate(){
+            writeln!(f, "{:>2}: {}", i+1, m)?;
+        }
+        Ok(())
+    }
 }
 
 // the hexagonal board of sidelength 6 is represented as an 11x11 array.
 // visualize it as taking the square and sorta moving the top edge to the right.
 // the board is accessed using board[x][y] from the bottom left
+#[derive(Clone)]
 pub struct GameState{
     board: [[Option<Piece>; 11]; 11],
-    metals_taken: u8
+    metals_taken: u8,
+    history: Vec<Move>, // moves made through apply_move, most recent last
+    undone: Vec<Move>, // moves that can be brought back with redo
+    rules: RuleSet
 }
 
 impl GameState{
     pub fn new() -> GameState{
+        GameState::with_rules(RuleSet::standard())
+    }
+
+    pub fn with_rules(rules: RuleSet) -> GameState{
         GameState{
             board: [[None; 11]; 11], // depending on how this works, the rows may be pointers to the same mem address
-            metals_taken: 0u8
+            metals_taken: 0u8,
+            history: Vec::new(),
+            undone: Vec::new(),
+            rules
         }
     }
 
     // same as Game1.png
     pub fn example() -> GameState{
-        GameState{
-            board: [[None, None, None, None, None, Some(Piece::Element(Element::Air)), None, None, None, None, Some(Piece::Element(Element::Water))], [None, None, None, None, None, Some(Piece::Element(Element::Earth)), Some(Piece::Mors), Some(Piece::Salt), Some(Piece::Metal(4)), Some(Piece::Element(Element::Fire)), None], [None, None, None, None, Some(Piece::Element(Element::Water)), Some(Piece::Vitae), Some(Piece::Element(Element::Air)), None, Some(Piece::Quicksilver), Some(Piece::Metal(3)), None], [None, None, None, Some(Piece::Vitae), None, Some(Piece::Element(Element::Fire)), Some(Piece::Element(Element::Fire)), Some(Piece::Element(Element::Earth)), Some(Piece::Metal(1)), Some(Piece::Metal(2)), None], [None, None, Some(Piece::Mors), Some(Piece::Element(Element::Air)), Some(Piece::Element(Element::Air)), None, None, Some(Piece::Element(Element::Fire)), None, Some(Piece::Element(Element::Fire)), None], [Some(Piece::Element(Element::Earth)), Some(Piece::Element(Element::Earth)), Some(Piece::Element(Element::Earth)), Some(Piece::Element(Element::Earth)), None, Some(Piece::Metal(5)), None, Some(Piece::Salt), Some(Piece::Element(Element::Water)), Some(Piece::Metal(0)), Some(Piece::Element(Element::Fire))], [None, Some(Piece::Vitae), None, Some(Piece::Salt), None, None, Some(Piece::Element(Element::Earth)), Some(Piece::Element(Element::Air)), Some(Piece::Mors), None, None], [None, Some(Piece::Quicksilver), Some(Piece::Element(Element::Earth)), Some(Piece::Element(Element::Water)), Some(Piece::Element(Element::Water)), Some(Piece::Element(Element::Air)), None, Some(Piece::Element(Element::Water)), None, None, None], [None, Some(Piece::Element(Element::Water)), Some(Piece::Element(Element::Fire)), None, Some(Piece::Quicksilver), Some(Piece::Mors), Some(Piece::Element(Element::Water)), None, None, None, None], [None, Some(Piece::Quicksilver), Some(Piece::Vitae), Some(Piece::Element(Element::Fire)), Some(Piece::Salt), Some(Piece::Quicksilver), None, None, None, None, None], [Some(Piece::Element(Element::Air)), None, None, None, None, Some(Piece::Element(Element::Air)), None, None, None, None, None]],
-            metals_taken: 0u8,
+        "
+                 W . . . . F
+                . F 3 2 F 0 .
+               . 4 Q 1 . W M .
+              . S . E F S A W .
+             . M A F . . E . W .
+            A E V F . 5 . A M Q A
+             . W . A . . W Q S .
+              . V A E S W . F .
+               . M E . E F V .
+                . E V Q W Q .
+                 E . . . . A
+        ".parse().expect("Example board failed to parse")
+    }
+
+    pub fn metals_taken(&self) -> u8{
+        self.metals_taken
+    }
+
+    // editing the board directly forgets the moves made, which may not fit the new board
+    pub fn set_metals_taken(&mut self, metals_taken: u8){
+        self.metals_taken = metals_taken;
+        self.history.clear();
+        self.undone.clear();
+    }
+
+    pub fn rules(&self) -> &RuleSet{
+        &self.rules
+    }
+
+    // switches to other rules, as long as every piece is still on the board under them
+    pub fn set_rules(&mut self, rules: RuleSet) -> Result<(), String>{
+        for x in 0..11{
+            for y in 0..11{
+                if self.board[x][y].is_some() && !rules.on_board(x, y){
+                    return Err(format!("({}, {}) is off the board under the new rules", x, y));
+                }
+            }
         }
+        self.rules = rules;
+        Ok(())
     }
 
+    // the cells of the 11x11 array that are part of the largest board any rules allow
     pub fn on_board(x: usize, y: usize) -> bool{
-        (x+y >= 5) && (x+y <= 15) && (x<11) && (y<11)
+        x < 11 && y < 11 && Hex::from_index(x, y).on_board()
     }
 
     pub fn get_piece(&self, x: usize, y: usize) -> Option<&Piece>{
@@ -91,8 +329,12 @@ impl GameState{
     pub fn set_piece(&mut self, piece: Option<Piece>, x: usize, y: usize) -> Result<Option<&Piece>, String>{
         if x >= 11 || y >= 11{
             Err(String::from("Attempted to place piece out of bounds"))
+        }else if piece.is_some() && !self.rules.on_board(x, y){
+            Err(format!("Attempted to place piece off the board at ({}, {})", x, y))
         }else{
             self.board[x][y] = piece;
+            self.history.clear();
+            self.undone.clear();
             Ok(self.board[x][y].as_ref())
         }
     }
@@ -122,13 +364,8 @@ impl GameState{
                 }
 
                 let mut neighbors = [false; 6];
-                for (i, d) in [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)].iter().enumerate(){
-                    if (x as i32 + d.0) < 0 || (y as i32 + d.1) < 0{
-                        continue;
-                    }
-                    let (xnew, ynew) = ((x as i32 + d.0) as usize,
-                                        (y as i32 + d.1) as usize);
-                    if GameState::on_board(xnew, ynew){
+                for (i, h) in Hex::from_index(x, y).neighbors().iter().enumerate(){
+                    if let Some((xnew, ynew)) = h.to_index(){
                         if self.board[xnew][ynew].is_some(){
                             neighbors[i] = true;
                         }
@@ -157,9 +394,7 @@ impl GameState{
         for x in 0..11{
             for y in 0..11{
                 if self.board[x][y].is_some(){
-                    if (x, y) != (5, 5){
-                        ans = false;
-                    }
+                    ans = false;
                 }
             }
         }
@@ -167,64 +402,560 @@ impl GameState{
         ans
     }
 
+    // how many of each kind of piece are left on the board
+    pub fn inventory(&self) -> HashMap<Piece, usize>{
+        let mut counts = HashMap::new();
+        for x in 0..11{
+            for y in 0..11{
+                if let Some(p) = self.board[x][y]{
+                    *counts.entry(p).or_insert(0) += 1;
+                }
+            }
+        }
+
+        counts
+    }
+
+    // a cheap check that only looks at which pieces are left, not where they are.
+    // false means the board can never be cleared; true means it might be.
+    pub fn could_be_solvable(&self) -> bool{
+        let counts = self.inventory();
+        let count = |p: Piece| *counts.get(&p).unwrap_or(&0);
+
+        // counting only says something under the real game's pairs. there, elements pair with
+        // themselves, so every element with an odd count needs a salt, and the salt left
+        // after that has to pair up with itself or an element
+        if self.rules.has_standard_pairs(){
+            let salt = count(Piece::Salt);
+            let odd_elements = [Element::Water, Element::Fire, Element::Earth, Element::Air].iter()
+                .filter(|e| count(Piece::Element(**e)) % 2 == 1)
+                .count();
+            if odd_elements > salt || (salt - odd_elements) % 2 == 1{
+                return false;
+            }
+
+            if count(Piece::Vitae) != count(Piece::Mors){
+                return false;
+            }
+
+            // gold goes on its own, every other metal needs a quicksilver
+            let metals: usize = (0..self.rules.gold()).map(|m| count(Piece::Metal(m))).sum();
+            if count(Piece::Quicksilver) != metals{
+                return false;
+            }
+        }
+
+        // anything else needs at least one partner left on the board
+        counts.iter().all(|(p, n)| {
+            *n == 0 || self.rules.is_gold(p) || counts.iter().any(|(q, m)| {
+                self.rules.legal_pair(p, q) && (*m >= 2 || (p != q && *m >= 1))
+            })
+        })
+    }
+
     pub fn print(&self){
-        // println!("{:?}", self.board);
+        print!("{}", self);
+    }
 
-        for y in (0..11).rev(){
-            for _ in 0..y{
-                print!(" ");
+    // a copy of the board with every piece moved by sym. the history is not carried over.
+    pub fn transformed(&self, sym: &Symmetry) -> GameState{
+        let mut gs = GameState::with_rules(self.rules.clone());
+        gs.metals_taken = self.metals_taken;
+        for h in Hex::board(){
+            let (x, y) = h.to_index().expect("Board hex was off the board");
+            let (nx, ny) = sym.apply(h).to_index().expect("Symmetry moved a cell off the board");
+            gs.board[nx][ny] = self.board[x][y];
+        }
+
+        gs
+    }
+
+    // picks one board to stand for all 12 rotations and reflections of this one, returning it
+    // along with the symmetry that takes this board to it. a solution of the canonical board
+    // solves this one after mapping it back with the inverse symmetry.
+    pub fn canonical(&self) -> (GameState, Symmetry){
+        let cells = Hex::board();
+        let key = |gs: &GameState| -> Vec<usize>{
+            cells.iter().map(|h| {
+                let (x, y) = h.to_index().expect("Board hex was off the board");
+                gs.board[x][y].map_or(0, |p| p.index() + 1)
+            }).collect()
+        };
+
+        let mut best = (self.transformed(&Symmetry::IDENTITY), Symmetry::IDENTITY);
+        let mut bestkey = key(&best.0);
+        for sym in Symmetry::all(){
+            let gs = self.transformed(&sym);
+            let k = key(&gs);
+            if k < bestkey{
+                best = (gs, sym);
+                bestkey = k;
             }
-            for x in 0..11{
-                print!("{} ", match self.get_piece(x, y){
-                    Some(e) => e.tochar(),
-                    None => String::from(" "),
-                });
+        }
+
+        best
+    }
+
+    // the same for every rotation and reflection of a board
+    pub fn canonical_hash(&self) -> u64{
+        self.canonical().0.zobrist_hash()
+    }
+
+    // zobrist hash of the board and metals_taken, as used by the transposition table
+    pub fn zobrist_hash(&self) -> u64{
+        let mut hash = transposition::metals_key(self.metals_taken);
+        for x in 0..11{
+            for y in 0..11{
+                if let Some(p) = &self.board[x][y]{
+                    hash ^= transposition::piece_key(p, x, y);
+                }
             }
-            println!("");
         }
+
+        hash
     }
 
     // solves in place, but should return the board to its original state by the end of execution
-    pub fn solve(&mut self) -> Option<Vec<[usize; 4]>>{
+    pub fn solve(&mut self) -> Option<Solution>{
+        let mut table = TranspositionTable::default();
+        self.solve_with_table(&mut table)
+    }
+
+    // same as solve, but dead positions are remembered in (and looked up from) the given table.
+    // the table can be reused between calls on the same board.
+    pub fn solve_with_table(&mut self, table: &mut TranspositionTable) -> Option<Solution>{
+        let hash = self.zobrist_hash();
+        self.solve_hashed(hash, table).map(|mut moves| {
+            moves.reverse();
+            Solution::from(moves)
+        })
+    }
+
+    // the moves come back in reverse order so they can be pushed on the way up
+    fn solve_hashed(&mut self, hash: u64, table: &mut TranspositionTable) -> Option<Vec<Move>>{
         if self.is_solved() {
-            Some(Vec::from([[5, 5, 5, 5]]))
+            Some(Vec::new())
+        }else if table.is_dead(hash) || !self.could_be_solvable(){
+            None
         }else{
-             let open_pieces = self.open_pieces();
-             let mut loopresult = None;
-
-             'outerpair:
-             for i in 0..open_pieces.len(){
-                 let ipos = open_pieces[i];
-                 let ipiece = self.board[ipos.0][ipos.1].expect("Open piece was None");
-                 for j in 0..open_pieces.len(){
-                     let jpos = open_pieces[j];
-                     let jpiece = self.board[jpos.0][jpos.1]
-                        .expect("Open piece was None");
-                     if i != j && ipiece.legal_pair(&jpiece){
-                         let metalchange = match (ipiece, jpiece){
-                             (_, Piece::Metal(_)) => 1,
-                             (Piece::Metal(_), _) => 1,
-                             _other => 0
-                         };
-                         self.metals_taken += metalchange;
-                         self.set_piece(None, ipos.0, ipos.1).expect("Failed to place piece in solve");
-                         self.set_piece(None, jpos.0, jpos.1).expect("Failed to place piece in solve");
-                         let rec = self.solve();
-                         self.metals_taken -= metalchange;
-                         self.set_piece(Some(ipiece), ipos.0, ipos.1).expect("Failed to place piece in solve");
-                         self.set_piece(Some(jpiece), jpos.0, jpos.1).expect("Failed to place piece in solve");
-
-                         if let Some(mut v) = rec{
-                             v.insert(0, [ipos.0, ipos.1, jpos.0, jpos.1]);
-                             loopresult = Some(v);
-                             break 'outerpair;
-                         }
-                     }
-                 }
-             }
-
-             loopresult
+            for action in self.legal_moves(){
+                let newhash = self.take_move(&action, hash);
+                let rec = self.solve_hashed(newhash, table);
+                self.put_back_move(&action);
+
+                if let Some(mut v) = rec{
+                    v.push(action);
+                    return Some(v);
+                }
+            }
+
+            table.mark_dead(hash);
+            None
+        }
+    }
+
+    // takes the pieces of a move off the board and returns the new zobrist hash
+    fn take_move(&mut self, action: &Move, hash: u64) -> u64{
+        let mut newhash = hash;
+        for c in action.cells(){
+            newhash ^= transposition::piece_key(&c.piece, c.x, c.y);
+            self.board[c.x][c.y] = None;
+        }
+        if action.takes_metal(){
+            newhash ^= transposition::metals_key(self.metals_taken)
+                ^ transposition::metals_key(self.metals_taken + 1);
+            self.metals_taken += 1;
+        }
+
+        newhash
+    }
+
+    // undoes take_move
+    fn put_back_move(&mut self, action: &Move){
+        if action.takes_metal(){
+            self.metals_taken -= 1;
+        }
+        for c in action.cells(){
+            self.board[c.x][c.y] = Some(c.piece);
+        }
+    }
+
+    // checks that a move could be played right now, explaining why not if it can't
+    pub fn check_move(&self, action: &Move) -> Result<(), String>{
+        for c in action.cells(){
+            if !self.rules.on_board(c.x, c.y){
+                return Err(format!("({}, {}) is not on the board", c.x, c.y));
+            }
+            match self.get_piece(c.x, c.y){
+                None => return Err(format!("There is no piece at ({}, {})", c.x, c.y)),
+                Some(p) if *p != c.piece => return Err(format!("Expected {:?} at ({}, {}) but found {:?}",
+                    c.piece, c.x, c.y, p)),
+                Some(_) => (),
+            }
+            if let Piece::Metal(m) = c.piece{
+                if m != self.metals_taken{
+                    return Err(format!("{} is locked until metal {} has been taken", c, self.metals_taken));
+                }
+            }
+            if !self.is_open(c.x, c.y){
+                return Err(format!("{} is not open", c));
+            }
+        }
+
+        match action{
+            Move::Pair(a, b) => {
+                if (a.x, a.y) == (b.x, b.y){
+                    Err(format!("{} can't be paired with itself", a))
+                }else if !self.rules.legal_pair(&a.piece, &b.piece){
+                    Err(format!("{} can't be paired with {}", a, b))
+                }else{
+                    Ok(())
+                }
+            },
+            Move::Single(a) => {
+                if !self.rules.is_gold(&a.piece){
+                    Err(format!("{} can't be removed on its own, only gold can", a))
+                }else{
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    // plays a move if it is legal. played moves can be taken back with undo.
+    pub fn apply_move(&mut self, action: &Move) -> Result<(), String>{
+        self.check_move(action)?;
+        self.take_move(action, 0);
+        self.history.push(*action);
+        self.undone.clear();
+
+        Ok(())
+    }
+
+    // takes back the last move made with apply_move
+    pub fn undo(&mut self) -> Option<Move>{
+        let action = self.history.pop()?;
+        self.put_back_move(&action);
+        self.undone.push(action);
+
+        Some(action)
+    }
+
+    // plays the last undone move again
+    pub fn redo(&mut self) -> Option<Move>{
+        let action = self.undone.pop()?;
+        self.take_move(&action, 0);
+        self.history.push(action);
+
+        Some(action)
+    }
+
+    // moves made with apply_move (and not undone), oldest first
+    pub fn history(&self) -> &[Move]{
+        &self.history
+    }
+
+    // every legal pair of open pieces, with each pair listed once, and open gold on its own
+    pub fn legal_moves(&self) -> Vec<Move>{
+        let open_pieces = self.open_pieces();
+        let mut ans = Vec::new();
+        for (i, &(ix, iy)) in open_pieces.iter().enumerate(){
+            let ipiece = self.board[ix][iy].expect("Open piece was None");
+            if self.rules.is_gold(&ipiece){
+                ans.push(Move::Single(Cell::new(ix, iy, ipiece)));
+            }
+            for &(jx, jy) in open_pieces.iter().skip(i+1){
+                let jpiece = self.board[jx][jy].expect("Open piece was None");
+                if self.rules.legal_pair(&ipiece, &jpiece){
+                    ans.push(Move::Pair(Cell::new(ix, iy, ipiece), Cell::new(jx, jy, jpiece)));
+                }
+            }
+        }
+
+        ans
+    }
+
+    // counts the complete clearing sequences. with merge_orderings, sequences that only
+    // differ in the order of independent moves count as one solution.
+    // two moves are independent when they don't share a cell. removing pieces only ever
+    // opens other pieces (and only one metal is open at a time), so independent moves can
+    // be played in either order and reach the same position.
+    // full boards have far more orderings than fit in a u64, and can take a long time to
+    // count; count_solutions_with_budget gives up instead.
+    pub fn count_solutions(&mut self, merge_orderings: bool) -> u128{
+        self.count_solutions_with_budget(merge_orderings, u64::MAX)
+            .expect("Counting ran out of an unlimited budget")
+    }
+
+    // like count_solutions, but gives None rather than look at more than node_budget positions
+    pub fn count_solutions_with_budget(&mut self, merge_orderings: bool, node_budget: u64) -> Option<u128>{
+        let hash = self.zobrist_hash();
+        let mut count = SolutionCount{
+            merge_orderings,
+            table: CountTable::default(),
+            nodes: 0,
+            node_budget
+        };
+        self.count_hashed(hash, &[], &mut count)
+    }
+
+    // the sleep set holds moves that were already explored from an earlier position and
+    // that would only lead to orderings we have already counted
+    fn count_hashed(&mut self, hash: u64, sleep: &[Move], count: &mut SolutionCount) -> Option<u128>{
+        if self.is_solved(){
+            return Some(1);
+        }else if !self.could_be_solvable(){
+            return Some(0);
+        }
+
+        // the same position counts differently with different moves asleep
+        let key = hash ^ transposition::sleep_key(sleep);
+        if let Some(n) = count.table.get(key){
+            return Some(n);
+        }
+        count.nodes += 1;
+        if count.nodes > count.node_budget{
+            return None;
         }
+
+        let mut total = 0;
+        let mut explored: Vec<Move> = Vec::new();
+        for action in self.legal_moves(){
+            if sleep.contains(&action){
+                continue;
+            }
+
+            let mut childsleep = Vec::new();
+            if count.merge_orderings{
+                childsleep = sleep.iter().chain(explored.iter())
+                    .filter(|m| !m.shares_cell(&action))
+                    .copied()
+                    .collect();
+            }
+
+            let newhash = self.take_move(&action, hash);
+            let below = self.count_hashed(newhash, &childsleep, count);
+            self.put_back_move(&action);
+            total += below?;
+
+            explored.push(action);
+        }
+
+        count.table.insert(key, total);
+        Some(total)
+    }
+
+    // calls found on every complete clearing sequence until it returns false.
+    // merge_orderings works the same as in count_solutions.
+    // returns how many solutions were passed to found.
+    pub fn for_each_solution<F>(&mut self, merge_orderings: bool, mut found: F) -> u64
+        where F: FnMut(&[Move]) -> bool{
+        let hash = self.zobrist_hash();
+        let mut stream = SolutionStream{
+            merge_orderings,
+            table: TranspositionTable::default(),
+            path: Vec::new(),
+            count: 0,
+            found: &mut found
+        };
+        self.stream_hashed(hash, &[], &mut stream);
+        stream.count
+    }
+
+    // returns false once found asks to stop
+    fn stream_hashed<F>(&mut self, hash: u64, sleep: &[Move], stream: &mut SolutionStream<F>) -> bool
+        where F: FnMut(&[Move]) -> bool{
+        if self.is_solved(){
+            stream.count += 1;
+            return (stream.found)(&stream.path);
+        }else if stream.table.is_dead(hash) || !self.could_be_solvable(){
+            return true;
+        }
+
+        let before = stream.count;
+        let mut explored: Vec<Move> = Vec::new();
+        for action in self.legal_moves(){
+            if sleep.contains(&action){
+                continue;
+            }
+
+            let mut childsleep = Vec::new();
+            if stream.merge_orderings{
+                childsleep = sleep.iter().chain(explored.iter())
+                    .filter(|m| !m.shares_cell(&action))
+                    .copied()
+                    .collect();
+            }
+
+            let newhash = self.take_move(&action, hash);
+            stream.path.push(action);
+            let keep_going = self.stream_hashed(newhash, &childsleep, stream);
+            stream.path.pop();
+            self.put_back_move(&action);
+
+            if !keep_going{
+                return false;
+            }
+            explored.push(action);
+        }
+
+        // with moves asleep, finding nothing here doesn't mean there is nothing to find
+        if stream.count == before && sleep.is_empty(){
+            stream.table.mark_dead(hash);
+        }
+        true
+    }
+}
+
+// what count_solutions carries down through count_hashed
+struct SolutionCount{
+    merge_orderings: bool,
+    table: CountTable,
+    nodes: u64, // positions looked at so far
+    node_budget: u64
+}
+
+// what for_each_solution carries down through stream_hashed
+struct SolutionStream<'a, F>{
+    merge_orderings: bool,
+    table: TranspositionTable,
+    path: Vec<Move>, // the moves from the starting board to the current one
+    count: u64,
+    found: &'a mut F
+}
+
+// where parsing a board failed. line and column count from 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoardParseError{
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+impl fmt::Display for BoardParseError{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+// the text column of cell (x, y). rows are drawn from y = 10 at the top down to y = 0,
+// with each row shifted half a cell so neighbours line up like on the screen.
+fn text_column(x: usize, y: usize) -> usize{
+    2*x + y - 5
+}
+
+// the board as 11 rows of symbols, '.' for an empty cell, followed by a
+// "metals taken: n" line if any metals have been taken
+impl fmt::Display for GameState{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        for y in (0..11).rev(){
+            let mut line = String::new();
+            for x in 0..11{
+                if !GameState::on_board(x, y){
+                    continue;
+                }
+                while line.len() < text_column(x, y){
+                    line.push(' ');
+                }
+                match self.get_piece(x, y){
+                    Some(p) => line.push_str(&p.tochar()),
+                    None => line.push('.'),
+                }
+            }
+            writeln!(f, "{}", line)?;
+        }
+        if self.metals_taken != 0{
+            writeln!(f, "metals taken: {}", self.metals_taken)?;
+        }
+        Ok(())
+    }
+}
+
+// reads what Display writes, under the standard rules. blank lines around the board and
+// indentation shared by every row are ignored, so boards can be written inline in the source.
+impl FromStr for GameState{
+    type Err = BoardParseError;
+
+    fn from_str(s: &str) -> Result<GameState, BoardParseError>{
+        GameState::parse_with_rules(s, RuleSet::standard())
+    }
+}
+
+impl GameState{
+    // like parse, but under rules: every piece has to be in their inventory and on one of
+    // their cells, and no more metals can be taken than their chain has
+    pub fn parse_with_rules(s: &str, rules: RuleSet) -> Result<GameState, BoardParseError>{
+        let error = |line: usize, column: usize, message: String| BoardParseError{line, column, message};
+
+        let lines: Vec<&str> = s.lines().map(|l| l.trim_end()).collect();
+        let first = match lines.iter().position(|l| !l.is_empty()){
+            Some(i) => i,
+            None => return Err(error(1, 1, String::from("No board found"))),
+        };
+        if lines.len() < first + 11{
+            return Err(error(lines.len() + 1, 1, format!("Expected 11 rows, found {}", lines.len() - first)));
+        }
+        let rows = &lines[first..(first + 11)];
+        let indent = rows.iter()
+            .filter(|l| !l.is_empty())
+            .map(|l| l.len() - l.trim_start().len())
+            .min()
+            .unwrap_or(0);
+
+        let mut gs = GameState::with_rules(rules);
+        for (i, row) in rows.iter().enumerate(){
+            let line = first + i + 1;
+            let y = 10 - i;
+            let mut seen = [false; 11];
+            for (col, c) in row.chars().enumerate(){
+                if c.is_whitespace(){
+                    continue;
+                }
+                // a symbol inside the shared indentation is always too far left
+                let misplaced = error(line, col + 1, format!("'{}' is not on a cell", c));
+                if col < indent || (col - indent + 5) < y || (col - indent + 5 - y) % 2 != 0{
+                    return Err(misplaced);
+                }
+                let x = (col - indent + 5 - y) / 2;
+                if !GameState::on_board(x, y){
+                    return Err(misplaced);
+                }
+                seen[x] = true;
+                if c != '.'{
+                    let piece = gs.rules.piece_from_char(c).ok_or_else(|| if c.is_ascii_digit(){
+                        error(line, col + 1, format!("There is no metal {}, gold is {}", c, gs.rules.gold()))
+                    }else if Piece::from_char(c).is_some(){
+                        error(line, col + 1, format!("'{}' isn't used under these rules", c))
+                    }else{
+                        error(line, col + 1, format!("Unknown piece '{}'", c))
+                    })?;
+                    if !gs.rules.on_board(x, y){
+                        return Err(error(line, col + 1, format!("({}, {}) is off the board under these rules", x, y)));
+                    }
+                    gs.board[x][y] = Some(piece);
+                }
+            }
+            if let Some(x) = (0..11).find(|x| GameState::on_board(*x, y) && !seen[*x]){
+                return Err(error(line, indent + text_column(x, y) + 1, format!("Missing cell ({}, {})", x, y)));
+            }
+        }
+
+        for (i, l) in lines.iter().enumerate().skip(first + 11){
+            let trimmed = l.trim();
+            if trimmed.is_empty(){
+                continue;
+            }
+            let column = l.len() - l.trim_start().len() + 1;
+            let taken = trimmed.strip_prefix("metals taken:")
+                .and_then(|n| n.trim().parse::<u8>().ok())
+                .ok_or_else(|| error(i + 1, column, format!("Unexpected line \"{}\"", trimmed)))?;
+            if taken > gs.rules.metals(){
+                return Err(error(i + 1, column, format!("Only {} metals can be taken, not {}", gs.rules.metals(), taken)));
+            }
+            gs.metals_taken = taken;
+        }
+
+        Ok(gs)
     }
 }
 
@@ -243,6 +974,20 @@ pub fn test(){
         assert!(!c.legal_pair(&d));
         assert!(!d.legal_pair(&d));
     }
+    {
+        // gold comes off by itself once the other metals are gone, wherever it is
+        let mut gs = GameState::new();
+        gs.set_piece(Some(Piece::Metal(GOLD)), 2, 8).unwrap();
+        assert!(!Piece::Metal(GOLD).legal_pair(&Piece::Quicksilver));
+        assert!(gs.legal_moves().is_empty());
+        gs.metals_taken = GOLD;
+        let gold = Move::Single(Cell::new(2, 8, Piece::Metal(GOLD)));
+        assert_eq!(gs.solve(), Some(Solution::from(vec![gold])));
+        assert!(!gs.is_solved());
+        Solution::from(vec![gold]).replay(&mut gs).unwrap();
+        assert!(gs.is_solved());
+        assert!(Solution::from(vec![gold]).replay(&mut gs).is_err());
+    }
     {
         let mut gs = GameState::new();
         let a = Some(Piece::Element(Element::Fire));
@@ -288,5 +1033,240 @@ pub fn test(){
                 assert_eq!(gs.get_piece(x, y), gs2.get_piece(x, y));
             }
         }
+        assert_eq!(gs.zobrist_hash(), gs2.zobrist_hash());
+    }
+    {
+        // a tiny table still finds the solution, it just forgets dead ends sooner
+        let mut gs = GameState::example();
+        let mut table = TranspositionTable::new(1024);
+        assert!(table.is_empty());
+        let solution = gs.solve_with_table(&mut table).expect("Example should be solvable");
+        assert!(table.len() <= 1024/32);
+        solution.replay(&mut gs).unwrap();
+        assert!(gs.is_solved());
+        let gs = GameState::example();
+
+        // two pairs removed in either order hash the same
+        let mut a = GameState::example();
+        let mut b = GameState::example();
+        a.set_piece(None, 0, 5).unwrap();
+        a.set_piece(None, 10, 0).unwrap();
+        b.set_piece(None, 10, 0).unwrap();
+        b.set_piece(None, 0, 5).unwrap();
+        assert_eq!(a.zobrist_hash(), b.zobrist_hash());
+        assert!(a.zobrist_hash() != gs.zobrist_hash());
+    }
+    {
+        let mut gs = GameState::new();
+        gs.set_piece(Some(Piece::Element(Element::Fire)), 4, 5).unwrap();
+        assert!(!gs.could_be_solvable());
+        gs.set_piece(Some(Piece::Salt), 6, 5).unwrap();
+        assert!(gs.could_be_solvable());
+        gs.set_piece(Some(Piece::Vitae), 4, 4).unwrap();
+        assert!(!gs.could_be_solvable());
+        gs.set_piece(Some(Piece::Mors), 6, 4).unwrap();
+        assert!(gs.could_be_solvable());
+        gs.set_piece(Some(Piece::Metal(GOLD)), 5, 5).unwrap();
+        assert!(gs.could_be_solvable());
+        gs.set_piece(Some(Piece::Quicksilver), 3, 3).unwrap();
+        assert!(!gs.could_be_solvable());
+        gs.set_piece(Some(Piece::Metal(4)), 7, 7).unwrap();
+        assert!(gs.could_be_solvable());
+
+        // swapping a vitae for a mors makes the example unsolvable
+        let mut gs = GameState::example();
+        assert!(gs.could_be_solvable());
+        gs.set_piece(Some(Piece::Mors), 2, 5).unwrap();
+        assert!(!gs.could_be_solvable());
+        assert!(gs.solve().is_none());
+    }
+    {
+        // two fires, two waters and two salts that never touch each other
+        let mut gs = GameState::new();
+        gs.set_piece(Some(Piece::Element(Element::Fire)), 0, 5).unwrap();
+        gs.set_piece(Some(Piece::Element(Element::Fire)), 0, 7).unwrap();
+        gs.set_piece(Some(Piece::Element(Element::Water)), 10, 5).unwrap();
+        gs.set_piece(Some(Piece::Element(Element::Water)), 10, 3).unwrap();
+        gs.set_piece(Some(Piece::Salt), 5, 0).unwrap();
+        gs.set_piece(Some(Piece::Salt), 5, 10).unwrap();
+
+        // by pairs: {FF, WW, SS}, then both fires or both waters each taking one salt,
+        // which can happen two ways
+        assert_eq!(gs.count_solutions(true), 5);
+        assert_eq!(gs.count_solutions_with_budget(true, 100), Some(5));
+        assert_eq!(gs.count_solutions_with_budget(false, 1), None);
+        let mut sets = HashSet::new();
+        let streamed = gs.for_each_solution(false, |s| {
+            let mut pairs: Vec<Move> = s.to_vec();
+            pairs.sort();
+            sets.insert(pairs);
+            true
+        });
+        assert_eq!(streamed as u128, gs.count_solutions(false));
+        assert_eq!(sets.len() as u128, gs.count_solutions(true));
+        assert_eq!(gs.for_each_solution(true, |_| true), 5);
+        assert_eq!(gs.for_each_solution(false, |_| false), 1);
+        assert_eq!(gs.get_piece(5, 0), Some(&Piece::Salt));
+
+        // a full board has too many to count quickly, so a budget stops it, with every
+        // piece put back
+        let mut full = GameState::example();
+        assert_eq!(full.count_solutions_with_budget(true, 20000), None);
+        assert_eq!(full.zobrist_hash(), GameState::example().zobrist_hash());
+
+        // counts are forgotten oldest first
+        let mut counts = CountTable::new(4 * 64);
+        assert!(counts.is_empty());
+        for k in 0..10{
+            counts.insert(k, k as u128);
+        }
+        assert_eq!(counts.len(), 4);
+        assert_eq!((counts.get(0), counts.get(9)), (None, Some(9)));
+    }
+    {
+        let mut gs = GameState::example();
+        let air = Cell::new(0, 5, Piece::Element(Element::Air));
+        let far_air = Cell::new(10, 0, Piece::Element(Element::Air));
+        let earth = Cell::new(5, 0, Piece::Element(Element::Earth));
+        let locked_earth = Cell::new(5, 2, Piece::Element(Element::Earth));
+        let lead = Cell::new(5, 9, Piece::Metal(0));
+        let tin = Cell::new(3, 8, Piece::Metal(1));
+        let quicksilver = Cell::new(9, 1, Piece::Quicksilver);
+        // clears what covers the lead
+        let opening = [
+            Move::Pair(air, far_air),
+            Move::Pair(Cell::new(1, 5, Piece::Element(Element::Earth)), earth),
+            Move::Pair(Cell::new(0, 10, Piece::Element(Element::Water)), Cell::new(2, 4, Piece::Element(Element::Water))),
+            Move::Pair(Cell::new(1, 6, Piece::Mors), Cell::new(2, 5, Piece::Vitae)),
+            Move::Pair(Cell::new(1, 7, Piece::Salt), Cell::new(1, 9, Piece::Element(Element::Fire))),
+            Move::Pair(Cell::new(2, 6, Piece::Element(Element::Air)), Cell::new(10, 5, Piece::Element(Element::Air))),
+            Move::Pair(Cell::new(3, 5, Piece::Element(Element::Fire)), Cell::new(5, 10, Piece::Element(Element::Fire))),
+        ];
+
+        assert!(gs.apply_move(&Move::Pair(air, earth)).is_err());
+        assert!(gs.apply_move(&Move::Pair(air, air)).is_err());
+        assert!(gs.apply_move(&Move::Pair(earth, locked_earth)).is_err());
+        assert!(gs.apply_move(&Move::Pair(tin, quicksilver)).is_err());
+        assert!(gs.apply_move(&Move::Single(air)).is_err());
+        assert!(gs.apply_move(&Move::Pair(Cell::new(0, 5, Piece::Salt), far_air)).is_err());
+        assert!(gs.history().is_empty());
+
+        gs.apply_move(&opening[0]).unwrap();
+        assert_eq!(gs.get_piece(0, 5), None);
+        assert!(gs.apply_move(&opening[0]).is_err());
+        for m in &opening[1..]{
+            gs.apply_move(m).unwrap();
+        }
+        assert_eq!(gs.metals_taken(), 0);
+        gs.apply_move(&Move::Pair(lead, quicksilver)).unwrap();
+        assert_eq!(gs.metals_taken(), 1);
+        assert_eq!(&gs.history()[..7], &opening[..]);
+        assert_eq!(gs.history().len(), 8);
+
+        assert_eq!(gs.undo(), Some(Move::Pair(lead, quicksilver)));
+        assert_eq!(gs.metals_taken(), 0);
+        assert_eq!(gs.get_piece(9, 1), Some(&Piece::Quicksilver));
+        while gs.undo().is_some(){}
+        assert_eq!(gs.zobrist_hash(), GameState::example().zobrist_hash());
+        assert_eq!(gs.redo(), Some(Move::Pair(air, far_air)));
+        assert_eq!(gs.get_piece(0, 5), None);
+
+        // making a new move forgets what was undone
+        gs.apply_move(&opening[1]).unwrap();
+        assert_eq!(gs.redo(), None);
+
+        // and so does editing the board, which the old moves may no longer fit
+        gs.undo();
+        gs.undo();
+        assert!(gs.solve().is_some());
+        assert_eq!(gs.redo(), Some(opening[0]));
+        gs.undo();
+        gs.set_piece(None, 10, 0).unwrap();
+        assert_eq!(gs.redo(), None);
+        gs.set_piece(Some(far_air.piece), 10, 0).unwrap();
+        gs.apply_move(&opening[0]).unwrap();
+        gs.set_metals_taken(0);
+        assert!(gs.history().is_empty());
+        assert_eq!(gs.undo(), None);
+
+        // a solution played backwards breaks on its first move
+        let mut gs = GameState::example();
+        let solution = gs.solve().unwrap();
+        let backwards: Vec<Move> = solution.iter().rev().copied().collect();
+        assert!(Solution::from(backwards).replay(&mut gs).is_err());
+        assert!(gs.history().is_empty());
+        solution.replay(&mut gs).unwrap();
+        assert!(gs.is_solved());
+        assert_eq!(gs.history().len(), solution.len());
+        assert!(!solution.is_empty() && Solution::from(Vec::new()).is_empty());
+    }
+    {
+        let gs = GameState::example();
+        let (canon, sym) = gs.canonical();
+        for other in Symmetry::all(){
+            let moved = gs.transformed(&other);
+            assert_eq!(moved.canonical_hash(), canon.zobrist_hash());
+            assert_eq!(moved.inventory(), gs.inventory());
+        }
+        assert_eq!(canon.transformed(&sym.inverse()).zobrist_hash(), gs.zobrist_hash());
+
+        // a solution found on the canonical board maps back onto the original
+        let mut canon = canon;
+        let solution = canon.solve().expect("Canonical example should be solvable");
+        let mut gs = gs;
+        solution.transformed(&sym.inverse()).replay(&mut gs).unwrap();
+        assert!(gs.is_solved());
+    }
+    {
+        for p in RuleSet::standard().inventory().iter().map(|(p, _)| p){
+            let c = p.tochar();
+            assert_eq!(c.len(), 1);
+            assert_eq!(Piece::from_char(c.chars().next().unwrap()), Some(*p));
+        }
+        assert_eq!(Piece::from_char('x'), None);
+        assert_eq!(Piece::from_char('5'), Some(Piece::Metal(GOLD)));
+        assert_eq!(Piece::from_char('9'), None);
+
+        let mut gs = GameState::example();
+        let text = gs.to_string();
+        assert_eq!(text.lines().count(), 11);
+        let parsed: GameState = text.parse().unwrap();
+        assert_eq!(parsed.zobrist_hash(), gs.zobrist_hash());
+
+        gs.set_metals_taken(2);
+        gs.set_piece(None, 5, 5).unwrap();
+        let parsed: GameState = gs.to_string().parse().unwrap();
+        assert_eq!(parsed.metals_taken(), 2);
+        assert_eq!(parsed.zobrist_hash(), gs.zobrist_hash());
+        assert_eq!(parsed.to_string(), gs.to_string());
+    }
+    {
+        let text = GameState::example().to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        let with_row = |row: usize, new: &str| -> String{
+            let mut v = lines.clone();
+            v[row] = new;
+            v.join("\n")
+        };
+
+        let err = with_row(0, "     W . . . . X").parse::<GameState>().err().expect("Board should not parse");
+        assert_eq!((err.line, err.column), (1, 16));
+        let err = with_row(0, "     W . . . .  F").parse::<GameState>().err().expect("Board should not parse");
+        assert_eq!((err.line, err.column), (1, 17));
+        let err = with_row(5, "A E V F . 5 . A M Q").parse::<GameState>().err().expect("Board should not parse");
+        assert_eq!((err.line, err.column), (6, 21));
+        let err = lines[..10].join("\n").parse::<GameState>().err().expect("Board should not parse");
+        assert_eq!(err.line, 11);
+        let err = format!("{}oops\n", text).parse::<GameState>().err().expect("Board should not parse");
+        assert_eq!((err.line, err.column), (12, 1));
+        assert!("".parse::<GameState>().is_err());
+
+        // metals past gold, on the board or taken
+        let err = with_row(0, "     W . . . . 9").parse::<GameState>().err().expect("Board should not parse");
+        assert_eq!((err.line, err.column), (1, 16));
+        assert!(err.message.contains("gold"));
+        assert!(format!("{}metals taken: 6\n", text).parse::<GameState>().is_ok());
+        let err = format!("{}metals taken: 99\n", text).parse::<GameState>().err().expect("Board should not parse");
+        assert_eq!(err.line, 12);
     }
 }
@@ -1,5 +1,6 @@
-use std::collections::HashSet;
 use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
 use std::thread;
 use std::time;
 
@@ -7,6 +8,16 @@ use image;
 use image::DynamicImage;
 use image::GenericImage;
 use image::GenericImageView;
+use image::Delay;
+use image::Frame;
+use image::Rgba;
+use image::RgbaImage;
+use image::AnimationDecoder;
+use image::codecs::gif::GifDecoder;
+use image::codecs::gif::GifEncoder;
+use image::codecs::gif::Repeat;
+use image::imageops;
+use image::imageops::FilterType;
 // use image::Pixel;
 
 use mouce::Mouse;
@@ -14,158 +25,377 @@ use mouce::common::MouseButton;
 
 use super::screenshot;
 use super::game;
+use super::rules::RuleSet;
 use super::image_manipulation;
+use super::geometry::BoardGeometry;
+use super::assignment;
+use super::locate;
+use super::templates;
+use super::templates::Templates;
 
-// In 1440x900 resolution, each marble is 52 pixels by 52 pixels
-// the horizontal offset in a row is 66
-// the vertical offset between rows is 57
-// the bottom of row 0 is at 173 (0 indexed from bottom)
-
-// converts board coordinates to screen coordinates (top left corner)
-fn get_screen_coords(xi: usize, yi: usize) -> (u32, u32){
-    let xi = xi as i32;
-    let yi = yi as i32;
-
-    let offsetvec = (456, 675);
-    let xivec = (66, 0);
-    let yivec = (33, -57);
-    let pos = (
-        (offsetvec.0 + xivec.0*xi + yivec.0*yi) as u32,
-        (offsetvec.1 + xivec.1*xi + yivec.1*yi) as u32
-    );
-
-    pos
-}
-
-// converts board coordinates to screen coordinates (approximate center of marble)
-fn get_screen_coords_center(xi: usize, yi: usize) -> (u32, u32){
-    let marblesize = (52, 52);
-    let topleftpos = get_screen_coords(xi, yi);
-    let pos = (
-        topleftpos.0 + marblesize.0/2,
-        topleftpos.1 + marblesize.1/2
-    );
-
-    pos
-}
-
-pub fn read_board(im: &DynamicImage) -> game::GameState{
-    let piece_images = [
-        (Some(game::Piece::Element(game::Element::Fire)), image::open("images/Pieces/Fire.png").unwrap()),
-        (Some(game::Piece::Element(game::Element::Water)), image::open("images/Pieces/Water.png").unwrap()),
-        (Some(game::Piece::Element(game::Element::Earth)), image::open("images/Pieces/Earth.png").unwrap()),
-        (Some(game::Piece::Element(game::Element::Air)), image::open("images/Pieces/Air.png").unwrap()),
-        (Some(game::Piece::Salt), image::open("images/Pieces/Salt.png").unwrap()),
-        (Some(game::Piece::Metal(0)), image::open("images/Pieces/Metal1.png").unwrap()),
-        (Some(game::Piece::Metal(1)), image::open("images/Pieces/Metal2.png").unwrap()),
-        (Some(game::Piece::Metal(2)), image::open("images/Pieces/Metal3.png").unwrap()),
-        (Some(game::Piece::Metal(3)), image::open("images/Pieces/Metal4.png").unwrap()),
-        (Some(game::Piece::Metal(4)), image::open("images/Pieces/Metal5.png").unwrap()),
-        (Some(game::Piece::Metal(5)), image::open("images/Pieces/Metal6.png").unwrap()),
-        (Some(game::Piece::Quicksilver), image::open("images/Pieces/Quicksilver.png").unwrap()),
-        (Some(game::Piece::Vitae), image::open("images/Pieces/Vitae.png").unwrap()),
-        (Some(game::Piece::Mors), image::open("images/Pieces/Mors.png").unwrap()),
-        (None, image::open("images/Pieces/Empty.png").unwrap())
-    ];
-
-    let mut gs = game::GameState::new();
-    let mut candidates = Vec::new();
+// how closely the template of a piece matched one cell of a screenshot. scores are image
+// differences, so lower is closer.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CellReading{
+    pub x: usize,
+    pub y: usize,
+    pub piece: Option<game::Piece>, // what was put on the board
+    pub score: f64,
+    pub runner_up: Option<game::Piece>, // the closest of everything else the inventory allows
+    pub runner_up_score: f64,
+    // how much worse the board's match would be with runner_up here, where at most one other
+    // cell changes to keep to the inventory
+    pub margin: f64
+}
+
+// cells read with a margin below this are worth a second look. every cell of the reference
+// screenshots Game1.png and Game3.png is read right with a margin of more than 0.09, while
+// Game2.png has a few, like two lit up airs that could nearly as well be two of its waters.
+pub const UNCERTAIN_MARGIN: f64 = 0.05;
+
+// more than any two images can differ by, since normalized differences average out below 2
+const UNREADABLE_COST: f64 = 4.0;
+
+pub struct BoardReading{
+    pub state: game::GameState,
+    pub cells: Vec<CellReading>
+}
+
+impl BoardReading{
+    // the cells whose margin is below threshold, least certain first
+    pub fn uncertain(&self, threshold: f64) -> Vec<CellReading>{
+        let mut ans: Vec<CellReading> = self.cells.iter().filter(|c| c.margin < threshold).copied().collect();
+        ans.sort_by(|a, b| a.margin.total_cmp(&b.margin));
+        ans
+    }
+}
+
+fn piece_name(piece: &Option<game::Piece>) -> String{
+    match piece{
+        Some(p) => p.tochar(),
+        None => String::from("."),
+    }
+}
+
+impl fmt::Display for CellReading{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
+        write!(f, "({}, {}): {} ({:.3}), then {} ({:.3}), margin {:.3}",
+            self.x, self.y, piece_name(&self.piece), self.score,
+            piece_name(&self.runner_up), self.runner_up_score, self.margin)
+    }
+}
+
+// geometry says where the board is in im, as found by locate::locate_board
+pub fn read_board(im: &DynamicImage, geometry: &BoardGeometry, rules: &RuleSet) -> Result<BoardReading, String>{
+    let piece_images = &templates::templates()?.pieces;
+    let marblesize = geometry.marble_pixels();
+
+    // how far each cell is from each template
+    let mut cell_scores = Vec::new();
 
     for xi in 0..11{
         for yi in 0..11{
-            if game::GameState::on_board(xi, yi){
-                let (x, y) = get_screen_coords(xi, yi);
-                for (piece, piece_im) in &piece_images{
-                    let imdiff = image_manipulation::image_diff_normalized_middle(&piece_im, &im.crop_imm(x, y, 52, 52));
+            if rules.on_board(xi, yi){
+                let (x, y) = geometry.cell_to_screen(xi, yi);
+                // the templates are 52x52, so other sizes of board are scaled to match them
+                let mut subim = im.crop_imm(x, y, marblesize, marblesize);
+                if marblesize != 52{
+                    subim = subim.resize_exact(52, 52, FilterType::Triangle);
+                }
+                let mut scores = Vec::new();
+                for (piece, piece_im) in piece_images{
+                    let imdiff = image_manipulation::image_diff_normalized_middle(piece_im, &subim);
+                    // a cell that can't be compared (like a flat crop, which gives NaN) is as
+                    // far from every template as it gets, so it never wins a piece
                     let imdiff = match imdiff {
-                        Ok(x) => x,
+                        Ok(x) if x.is_finite() => x,
+                        Ok(_) => UNREADABLE_COST,
                         Err(s) => {
                             println!("imdiff Error: {}", s);
-                            0.0
+                            UNREADABLE_COST
                         }
                     };
 
-                    candidates.push((imdiff, piece, xi, yi))
+                    scores.push((*piece, imdiff))
                 }
+                scores.sort_by(|a, b| a.1.total_cmp(&b.1));
+                cell_scores.push(((xi, yi), scores));
             }
         }
     }
 
-    let mut pieces_left = HashMap::from([
-        (game::Piece::Element(game::Element::Fire), 8),
-        (game::Piece::Element(game::Element::Water), 8),
-        (game::Piece::Element(game::Element::Earth), 8),
-        (game::Piece::Element(game::Element::Air), 8),
-        (game::Piece::Salt, 4),
-        (game::Piece::Metal(0), 1),
-        (game::Piece::Metal(1), 1),
-        (game::Piece::Metal(2), 1),
-        (game::Piece::Metal(3), 1),
-        (game::Piece::Metal(4), 1),
-        (game::Piece::Metal(5), 1),
-        (game::Piece::Quicksilver, 5),
-        (game::Piece::Vitae, 4),
-        (game::Piece::Mors, 4),
-    ]);
-
-    // candidates.sort_by_key(|a| a.0);
-    candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-    let mut placed_pieces = HashSet::new();
-
-    for c in candidates {
-        let (imdiff, piece, xi, yi) = c;
-        if !placed_pieces.contains(&(xi, yi)) {
-            let availible = match piece {
-                Some(p) => *pieces_left.entry(*p).or_insert(0) > 0,
-                None => true,
+    // one slot for every piece in the inventory and enough empty slots for every cell, then
+    // the cheapest way of giving each cell a slot. unlike taking the closest matches first,
+    // one bad match can't use up a piece that another cell needed.
+    let mut slots: Vec<Option<game::Piece>> = Vec::new();
+    for (p, n) in rules.inventory(){
+        if piece_images.iter().any(|(piece, _)| *piece == Some(*p)){
+            slots.extend(std::iter::repeat_n(Some(*p), *n));
+        }
+    }
+    slots.extend(std::iter::repeat_n(None, cell_scores.len()));
+    let costs: Vec<Vec<f64>> = cell_scores.iter()
+        .map(|(_, scores)| slots.iter()
+            .map(|slot| scores.iter().find(|(p, _)| p == slot).map(|(_, d)| *d).expect("Every slot has a template"))
+            .collect())
+        .collect();
+    let assignment = assignment::min_cost_assignment(&costs)?;
+
+    let cost = |cell: usize, piece: Option<game::Piece>| cell_scores[cell].1.iter()
+        .find(|(p, _)| *p == piece)
+        .map(|(_, d)| *d)
+        .expect("Every slot has a template");
+    let pieces: Vec<Option<game::Piece>> = assignment.iter().map(|slot| slots[*slot]).collect();
+    let mut kinds = slots.clone();
+    kinds.dedup();
+    // how many more of each piece the inventory has room for
+    let mut spare: HashMap<Option<game::Piece>, usize> = HashMap::new();
+    for slot in slots.iter(){
+        *spare.entry(*slot).or_insert(0) += 1;
+    }
+    for piece in pieces.iter(){
+        *spare.entry(*piece).or_insert(0) -= 1;
+    }
+
+    let mut gs = game::GameState::with_rules(rules.clone());
+    let mut cells = Vec::new();
+    for (i, ((xi, yi), _)) in cell_scores.iter().enumerate(){
+        let piece = pieces[i];
+        gs.set_piece(piece, *xi, *yi)?;
+
+        // the cheapest other reading of this cell that still keeps to the inventory with at
+        // most one other cell changing: a piece with one to spare, or one taken from another
+        // cell, which then reads as this cell's piece or something else with one to spare.
+        // longer chains of changes aren't tried, so this can overstate the margin.
+        let mut runner_up: Option<(Option<game::Piece>, f64)> = None;
+        for other in kinds.iter().filter(|k| **k != piece){
+            let change = if spare[other] > 0{
+                cost(i, *other) - cost(i, piece)
+            }else{
+                pieces.iter().enumerate()
+                    .filter(|(k, p)| *k != i && *p == other)
+                    .map(|(k, _)| {
+                        let moved = kinds.iter()
+                            .filter(|r| **r == piece || spare[*r] > 0)
+                            .map(|r| cost(k, *r))
+                            .fold(f64::INFINITY, f64::min);
+                        cost(i, *other) - cost(i, piece) + moved - cost(k, *other)
+                    })
+                    .fold(f64::INFINITY, f64::min)
             };
-            if availible {
-                // println!("{:?}: {:?} , {:?}", (xi, yi), piece, imdiff);
-                placed_pieces.insert((xi, yi));
-                if let Some(p) = piece{
-                    *pieces_left.entry(*p).or_insert(0) -= 1;
-                }
+            if runner_up.is_none_or(|(_, best)| change < best){
+                runner_up = Some((*other, change));
+            }
+        }
+        let (runner_up, margin) = runner_up.unwrap_or((None, f64::INFINITY));
+        cells.push(CellReading{
+            x: *xi, y: *yi,
+            piece, score: cost(i, piece),
+            runner_up, runner_up_score: cost(i, runner_up),
+            margin
+        });
+    }
+
+    Ok(BoardReading{state: gs, cells})
+}
 
-                match gs.set_piece(*piece, xi as usize, yi as usize) {
-                    Ok(_) => (),
-                    Err(message) => panic!("{}", message)
-                };
+// a made up screenshot of gs: the empty board with each piece's template pasted where
+// read_board would look for it
+pub fn render_board(gs: &game::GameState) -> Result<DynamicImage, String>{
+    draw_board(gs, templates::templates()?)
+}
+
+fn draw_board(gs: &game::GameState, templates: &Templates) -> Result<DynamicImage, String>{
+    let mut im = templates.empty_board.clone();
+    for xi in 0..11{
+        for yi in 0..11{
+            if let Some(p) = gs.get_piece(xi, yi){
+                let template = templates.piece(Some(*p))
+                    .ok_or_else(|| format!("There is no image of {:?}", p))?;
+                let (x, y) = BoardGeometry::REFERENCE.cell_to_screen(xi, yi);
+                im.copy_from(template, x, y).map_err(|e| format!("Couldn't draw {:?}: {}", p, e))?;
             }
         }
+    }
+
+    Ok(im)
+}
 
+pub fn save_board_png(gs: &game::GameState, path: &str) -> Result<(), String>{
+    let im = render_board(gs)?;
+    im.save(path).map_err(|e| format!("Couldn't save {}: {}", path, e))
+}
+
+// draws a ring around the marble at (xi, yi)
+fn circle_cell(im: &mut RgbaImage, xi: usize, yi: usize){
+    let ring = Rgba([255, 200, 0, 255]);
+    let (x, y) = BoardGeometry::REFERENCE.cell_center(xi, yi);
+    let (cx, cy) = (x as i32, y as i32);
+    for dx in -30i32..=30{
+        for dy in -30i32..=30{
+            let d2 = dx*dx + dy*dy;
+            if (25*25..=29*29).contains(&d2){
+                let (px, py) = (cx + dx, cy + dy);
+                if px >= 0 && py >= 0 && (px as u32) < im.width() && (py as u32) < im.height(){
+                    im.put_pixel(px as u32, py as u32, ring);
+                }
+            }
+        }
     }
+}
+
+// the part of a rendered board the board takes up, as (x, y, width, height)
+fn board_area() -> (u32, u32, u32, u32){
+    let margin = 20;
+    let marblesize = BoardGeometry::REFERENCE.marble_pixels();
+    let cells: Vec<(u32, u32)> = (0..11)
+        .flat_map(|xi| (0..11).map(move |yi| (xi, yi)))
+        .filter(|(xi, yi)| game::GameState::on_board(*xi, *yi))
+        .map(|(xi, yi)| BoardGeometry::REFERENCE.cell_to_screen(xi, yi))
+        .collect();
+    let left = cells.iter().map(|c| c.0).min().expect("Board has no cells") - margin;
+    let top = cells.iter().map(|c| c.1).min().expect("Board has no cells") - margin;
+    let right = cells.iter().map(|c| c.0).max().expect("Board has no cells") + marblesize + margin;
+    let bottom = cells.iter().map(|c| c.1).max().expect("Board has no cells") + marblesize + margin;
+    (left, top, right - left, bottom - top)
+}
 
+// an animation of solution being played on gs, cropped to the board and shrunk to half size
+// to keep the file small. each move gets a frame with its pieces circled, then one with them gone.
+pub fn save_solution_gif(gs: &game::GameState, solution: &game::Solution, path: &str) -> Result<(), String>{
+    let templates = templates::templates()?;
+    let (left, top, width, height) = board_area();
+    let frame = |im: &RgbaImage, ms: u32| {
+        let board = im.view(left, top, width, height).to_image();
+        let small = imageops::resize(&board, width / 2, height / 2, FilterType::Triangle);
+        Frame::from_parts(small, 0, 0, Delay::from_numer_denom_ms(ms, 1))
+    };
+
+    let mut gs = gs.clone();
+    let mut frames = Vec::new();
+    frames.push(frame(&draw_board(&gs, templates)?.to_rgba8(), 1500));
+    for (i, action) in solution.iter().enumerate(){
+        let mut im = draw_board(&gs, templates)?.to_rgba8();
+        for c in action.cells(){
+            circle_cell(&mut im, c.x, c.y);
+        }
+        frames.push(frame(&im, 700));
+
+        gs.apply_move(action).map_err(|e| format!("Move {}: {}", i + 1, e))?;
+        let im = draw_board(&gs, templates)?.to_rgba8();
+        let delay = if i + 1 == solution.len(){ 2000 }else{ 300 };
+        frames.push(frame(&im, delay));
+    }
 
-    gs
+    let file = File::create(path).map_err(|e| format!("Couldn't create {}: {}", path, e))?;
+    let mut encoder = GifEncoder::new_with_speed(file, 20);
+    encoder.set_repeat(Repeat::Infinite).map_err(|e| format!("Couldn't write {}: {}", path, e))?;
+    encoder.encode_frames(frames).map_err(|e| format!("Couldn't write {}: {}", path, e))
 }
 
-pub fn perform_solution(solution: &Vec<[usize; 4]>){
+pub fn perform_solution(solution: &game::Solution, geometry: &BoardGeometry){
     let mouse_manager = Mouse::new();
     let clickdelay = time::Duration::from_millis(500);
 
     for action in solution{
-        let (x, y) = get_screen_coords_center(action[0], action[1]);
-        mouse_manager.move_to(x as usize, y as usize)
-            .expect(&format!("Attempted to move to ({}, {}) -> ({}, {})",
-                action[0], action[1], x, y)[..]);
-        thread::sleep(clickdelay);
-        mouse_manager.click_button(&MouseButton::Left).expect("Attempted to click");
-        thread::sleep(clickdelay);
-
-        let (x, y) = get_screen_coords_center(action[2], action[3]);
-        mouse_manager.move_to(x as usize, y as usize)
-            .expect(&format!("Attempted to move to ({}, {}) -> ({}, {})",
-                action[2], action[3], x, y)[..]);
-        thread::sleep(clickdelay);
-        mouse_manager.click_button(&MouseButton::Left).expect("Attempted to click");
-        thread::sleep(clickdelay);
+        // gold only gets the one click
+        for cell in action.cells(){
+            let (x, y) = geometry.cell_center(cell.x, cell.y);
+            mouse_manager.move_to(x as usize, y as usize)
+                .expect(&format!("Attempted to move to ({}, {}) -> ({}, {})",
+                    cell.x, cell.y, x, y)[..]);
+            thread::sleep(clickdelay);
+            mouse_manager.click_button(&MouseButton::Left).expect("Attempted to click");
+            thread::sleep(clickdelay);
+        }
     }
 }
 
 pub fn test(){
+    // Game3 reads right, and is sure of every cell
     let im = image::open("images/Game3.png").unwrap();
-    let gs = read_board(&im);
-    gs.print();
+    let geometry = locate::locate_board(&im).unwrap();
+    let reading = read_board(&im, &geometry, &RuleSet::standard()).unwrap();
+    let game3: game::GameState = "
+             . Q . . . .
+            . E E E A F W
+           . M . S E . S .
+          . M 4 F 3 E W F .
+         F V A A . . W A M .
+        . S . A . 5 . W . Q .
+         . 1 A E . . A F V F
+          . W Q S V E M A .
+           . 2 . 0 Q . W .
+            V F W E Q W .
+             . . . . F .
+    ".parse().unwrap();
+    assert_eq!(reading.state.to_string(), game3.to_string());
+    assert!(reading.uncertain(UNCERTAIN_MARGIN).is_empty());
+
+    // Game1 reads right, but its lit up airs only because there is just the one 4
+    let im = image::open("images/Game1.png").unwrap();
+    let reading = read_board(&im, &locate::locate_board(&im).unwrap(), &RuleSet::standard()).unwrap();
+    assert_eq!(reading.state.to_string(), game::GameState::example().to_string());
+    assert_eq!(reading.cells.len(), 91);
+    // and, going by what the inventory allows, is sure of every cell
+    assert!(reading.uncertain(UNCERTAIN_MARGIN).is_empty());
+    assert!(reading.cells.iter().all(|c| c.runner_up != c.piece && c.margin > 0.0));
+    let unsure = reading.uncertain(1.0);
+    assert!(unsure.windows(2).all(|w| w[0].margin <= w[1].margin));
+    assert!(unsure.iter().all(|c| c.margin < 1.0));
+
+    // Game2's lit up airs are flagged
+    let im = image::open("images/Game2.png").unwrap();
+    let reading = read_board(&im, &locate::locate_board(&im).unwrap(), &RuleSet::standard()).unwrap();
+    let uncertain = reading.uncertain(UNCERTAIN_MARGIN);
+    let air = Some(game::Piece::Element(game::Element::Air));
+    let water = Some(game::Piece::Element(game::Element::Water));
+    assert!(uncertain.len() >= 2 && uncertain[..2].iter().all(|c| c.piece == air && c.runner_up == water));
+
+    // a rendered board reads back as the same board
+    let example = game::GameState::example();
+    let rendered = render_board(&example).unwrap();
+    assert_eq!((rendered.width(), rendered.height()), (1440, 900));
+    let reading = read_board(&rendered, &BoardGeometry::REFERENCE, &RuleSet::standard()).unwrap();
+    assert_eq!(reading.state.to_string(), example.to_string());
+    // and is sure of every cell, having been drawn from the templates
+    assert!(reading.uncertain(UNCERTAIN_MARGIN).is_empty());
+    assert!(reading.cells.iter().all(|c| c.runner_up != c.piece && c.margin > 0.0));
+
+    // a flat cell can't be compared with anything, but still reads without panicking, as the
+    // empty cell it is, since every other cell wants its piece more
+    let (xi, yi) = (0..11).flat_map(|x| (0..11).map(move |y| (x, y)))
+        .find(|(x, y)| game::GameState::on_board(*x, *y) && example.get_piece(*x, *y).is_none())
+        .unwrap();
+    let mut flat = rendered.clone();
+    let (x, y) = BoardGeometry::REFERENCE.cell_to_screen(xi, yi);
+    for dx in 0..52{
+        for dy in 0..52{
+            flat.put_pixel(x + dx, y + dy, Rgba([40, 40, 40, 255]));
+        }
+    }
+    let reading = read_board(&flat, &BoardGeometry::REFERENCE, &RuleSet::standard()).unwrap();
+    assert_eq!(reading.state.to_string(), example.to_string());
+    let cell = reading.cells.iter().find(|c| (c.x, c.y) == (xi, yi)).unwrap();
+    assert_eq!(cell.score, UNREADABLE_COST);
+
+    // and so does a shrunken copy of it, once the board is found
+    let small = rendered.resize_exact(1080, 675, FilterType::Triangle);
+    let geometry = locate::locate_board(&small).unwrap();
+    let read = read_board(&small, &geometry, &RuleSet::standard()).unwrap().state;
+    assert_eq!(read.to_string(), example.to_string());
+
+    // the first few moves of its solution, as two frames a move plus the starting board
+    let solution = example.clone().solve().unwrap();
+    let start = game::Solution::from(solution.moves()[..3].to_vec());
+    let path = std::env::temp_dir().join("auto_sigmar_test.gif");
+    let path = path.to_str().unwrap();
+    save_solution_gif(&example, &start, path).unwrap();
+    let frames = GifDecoder::new(File::open(path).unwrap()).unwrap().into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 7);
+    let backwards = game::Solution::from(solution.moves().iter().rev().copied().collect::<Vec<_>>());
+    assert!(save_solution_gif(&example, &backwards, path).unwrap_err().starts_with("Move 1"));
+    std::fs::remove_file(path).unwrap();
 }
 
 pub fn mousetest(){
@@ -178,7 +408,7 @@ pub fn mousetest(){
     for xi in 0..11{
         for yi in 0..11{
             if game::GameState::on_board(xi, yi){
-                let (x, y) = get_screen_coords_center(xi, yi);
+                let (x, y) = BoardGeometry::REFERENCE.cell_center(xi, yi);
                 mouse_manager.move_to(x as usize, y as usize).expect("Attempted move");
                 mouse_manager.move_relative(1, 1).expect("Attempted move 2");
                 thread::sleep(time::Duration::from_millis(100));
@@ -197,10 +427,10 @@ pub fn oldtest(){
     for xi in 0..11{
         for yi in 0..11{
             if game::GameState::on_board(xi, yi){
-                let (x, y) = get_screen_coords(xi, yi);
+                let (x, y) = BoardGeometry::REFERENCE.cell_to_screen(xi, yi);
                 im.put_pixel(x, y, image::Rgba::<u8>([0, 255, 0, 255]));
 
-                let (x, y) = get_screen_coords_center(xi, yi);
+                let (x, y) = BoardGeometry::REFERENCE.cell_center(xi, yi);
                 im.put_pixel(x, y, image::Rgba::<u8>([255, 0, 0, 255]));
             }
         }
@@ -214,7 +444,7 @@ pub fn oldtest(){
     for xi in 0..11{
         for yi in 0..11{
             if game::GameState::on_board(xi, yi){
-                let (x, y) = get_screen_coords(xi, yi);
+                let (x, y) = BoardGeometry::REFERENCE.cell_to_screen(xi, yi);
 
                 let mc_subim = image_manipulation::max_contrast_grayscale(&im.crop_imm(x, y, 52, 52));
                 for dx in 0..52{
@@ -0,0 +1,274 @@
+use super::game::Element;
+use super::game::GameState;
+use super::game::Move;
+use super::game::Piece;
+use super::hex::Hex;
+use super::rules::RuleSet;
+
+// a small deterministic PRNG (splitmix64), so the same seed gives the same board everywhere
+pub struct Rng{
+    state: u64
+}
+
+impl Rng{
+    pub fn new(seed: u64) -> Rng{
+        Rng{state: seed}
+    }
+
+    pub fn next_u64(&mut self) -> u64{
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // a number in 0..n
+    pub fn below(&mut self, n: usize) -> usize{
+        (self.next_u64() % (n as u64)) as usize
+    }
+
+    pub fn shuffle<T>(&mut self, v: &mut [T]){
+        for i in (1..v.len()).rev(){
+            let j = self.below(i + 1);
+            v.swap(i, j);
+        }
+    }
+}
+
+// gives up on a layout after this many dead ends and starts again
+const MAX_ATTEMPTS: usize = 1000;
+
+// makes a board with the standard inventory that is guaranteed to be solvable.
+// it picks a random order to clear the pieces in, then plays that order backwards from an
+// empty board, only ever putting pieces down where they would be open when removed.
+pub fn generate(seed: u64) -> GameState{
+    generate_with_rules(seed, &RuleSet::standard())
+}
+
+// the clearing order is built from the real game's pairings (salt with elements, vitae with
+// mors, quicksilver with metals), so the rules have to pair the same way
+pub fn generate_with_rules(seed: u64, rules: &RuleSet) -> GameState{
+    assert!(rules.has_standard_pairs(), "Boards can only be generated for the real game's pairs");
+    let mut rng = Rng::new(seed);
+    for _ in 0..MAX_ATTEMPTS{
+        let moves = clearing_order(rules, &mut rng);
+        if let Some(gs) = place_backwards(rules, &moves, &mut rng){
+            return gs;
+        }
+    }
+
+    panic!("Failed to generate a board for seed {}", seed);
+}
+
+// the groups of pieces that get removed together, in the order they are removed.
+// gold always goes last, since it sits in the middle like in the real game.
+fn clearing_order(rules: &RuleSet, rng: &mut Rng) -> Vec<Vec<Piece>>{
+    let count = |p: Piece| rules.count(&p);
+    let elements = [Element::Water, Element::Fire, Element::Earth, Element::Air];
+
+    let mut others = Vec::new();
+    for _ in 0..count(Piece::Vitae){
+        others.push(vec![Piece::Vitae, Piece::Mors]);
+    }
+
+    // an element with an odd count takes a salt for its odd one out. the rest of the salt
+    // either pairs with itself or, two at a time, with two of the same element
+    let mut element_counts: Vec<usize> = elements.iter().map(|e| count(Piece::Element(*e))).collect();
+    let mut salt = count(Piece::Salt);
+    for (i, e) in elements.iter().enumerate(){
+        if element_counts[i] % 2 == 1{
+            element_counts[i] -= 1;
+            salt -= 1;
+            others.push(vec![Piece::Salt, Piece::Element(*e)]);
+        }
+    }
+    while salt >= 2{
+        if rng.below(2) == 0{
+            others.push(vec![Piece::Salt, Piece::Salt]);
+        }else{
+            let e = rng.below(elements.len());
+            if element_counts[e] >= 2{
+                element_counts[e] -= 2;
+                others.push(vec![Piece::Salt, Piece::Element(elements[e])]);
+                others.push(vec![Piece::Salt, Piece::Element(elements[e])]);
+            }else{
+                others.push(vec![Piece::Salt, Piece::Salt]);
+            }
+        }
+        salt -= 2;
+    }
+    for (i, e) in elements.iter().enumerate(){
+        for _ in 0..(element_counts[i] / 2){
+            others.push(vec![Piece::Element(*e), Piece::Element(*e)]);
+        }
+    }
+    rng.shuffle(&mut others);
+
+    // the metals keep their order, but get spread out among everything else
+    let mut order = others;
+    let mut slots: Vec<usize> = (0..rules.gold()).map(|_| rng.below(order.len() + 1)).collect();
+    slots.sort();
+    for (m, slot) in slots.iter().enumerate().rev(){
+        order.insert(*slot, vec![Piece::Quicksilver, Piece::Metal(m as u8)]);
+    }
+    order.push(vec![Piece::Metal(rules.gold())]);
+
+    // RuleSet::new only lets through inventories that split up like this
+    let pieces: usize = rules.inventory().iter().map(|(_, n)| *n).sum();
+    assert_eq!(order.iter().map(|group| group.len()).sum::<usize>(), pieces, "Clearing order left pieces out");
+    order
+}
+
+// puts the moves back on an empty board, last move first. returns None if it paints
+// itself into a corner.
+fn place_backwards(rules: &RuleSet, moves: &[Vec<Piece>], rng: &mut Rng) -> Option<GameState>{
+    let mut gs = GameState::with_rules(rules.clone());
+    let cells = rules.cells();
+
+    // metals_taken just before each move is played
+    let mut metals_before = Vec::new();
+    let mut metals = 0;
+    for m in moves{
+        metals_before.push(metals);
+        if m.iter().any(|p| matches!(p, Piece::Metal(_))){
+            metals += 1;
+        }
+    }
+
+    for (i, m) in moves.iter().enumerate().rev(){
+        gs.set_metals_taken(metals_before[i]);
+
+        // gold is the first thing put down, so the middle is always free for it
+        if m.len() == 1 && i == moves.len() - 1{
+            let (x, y) = Hex::ORIGIN.to_index().expect("Middle was off the board");
+            gs.set_piece(Some(m[0]), x, y).expect("Failed to place piece.");
+            continue;
+        }
+
+        // cells touching pieces already down are tried first, so the board grows outwards
+        // from the middle as one clump like the real game's boards do
+        let mut empty: Vec<(usize, usize)> = cells.iter()
+            .filter_map(|h| h.to_index())
+            .filter(|(x, y)| gs.get_piece(*x, *y).is_none())
+            .collect();
+        rng.shuffle(&mut empty);
+        empty.sort_by_key(|(x, y)| {
+            !Hex::from_index(*x, *y).neighbors().iter()
+                .filter_map(|h| h.to_index())
+                .any(|(nx, ny)| gs.get_piece(nx, ny).is_some())
+        });
+
+        if !place_group(&mut gs, m, &empty){
+            return None;
+        }
+    }
+
+    gs.set_metals_taken(0);
+    Some(gs)
+}
+
+// puts a group of one or two pieces into empty cells so that all of them are open
+fn place_group(gs: &mut GameState, group: &[Piece], empty: &[(usize, usize)]) -> bool{
+    for (i, (x, y)) in empty.iter().enumerate(){
+        gs.set_piece(Some(group[0]), *x, *y).expect("Failed to place piece.");
+        if group.len() == 1{
+            if gs.is_open(*x, *y){
+                return true;
+            }
+        }else{
+            for (x2, y2) in empty[(i+1)..].iter(){
+                gs.set_piece(Some(group[1]), *x2, *y2).expect("Failed to place piece.");
+                if gs.is_open(*x, *y) && gs.is_open(*x2, *y2){
+                    return true;
+                }
+                gs.set_piece(None, *x2, *y2).expect("Failed to place piece.");
+            }
+        }
+        gs.set_piece(None, *x, *y).expect("Failed to place piece.");
+    }
+
+    false
+}
+
+pub fn test(){
+    {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        for _ in 0..100{
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert!(a.below(10) < 10);
+    }
+    {
+        for seed in 0..20{
+            let mut gs = generate(seed);
+            let inventory = gs.inventory();
+            for (p, n) in RuleSet::standard().inventory(){
+                assert_eq!(inventory.get(p), Some(n));
+            }
+            assert_eq!(gs.get_piece(5, 5), Some(&Piece::Metal(5)));
+            assert_eq!(gs.zobrist_hash(), generate(seed).zobrist_hash());
+
+            let solution = gs.solve().expect("Generated board should be solvable");
+            solution.replay(&mut gs).unwrap();
+            assert!(gs.is_solved());
+        }
+        assert!(generate(1).zobrist_hash() != generate(2).zobrist_hash());
+    }
+    {
+        // a practice board with three rings and a short metal chain
+        let inventory = vec![
+            (Piece::Element(Element::Fire), 4),
+            (Piece::Element(Element::Water), 4),
+            (Piece::Element(Element::Earth), 2),
+            (Piece::Element(Element::Air), 2),
+            (Piece::Salt, 2),
+            (Piece::Metal(0), 1),
+            (Piece::Metal(1), 1),
+            (Piece::Metal(2), 1),
+            (Piece::Quicksilver, 2),
+            (Piece::Vitae, 2),
+            (Piece::Mors, 2),
+        ];
+        let rules = RuleSet::new(3, inventory, 3, Piece::legal_pair).unwrap();
+        for seed in 0..20{
+            let mut gs = generate_with_rules(seed, &rules);
+            assert_eq!(gs.inventory().values().sum::<usize>(), 23);
+            assert_eq!(gs.get_piece(5, 5), Some(&Piece::Metal(2)));
+            for x in 0..11{
+                for y in 0..11{
+                    assert!(gs.get_piece(x, y).is_none() || rules.on_board(x, y));
+                }
+            }
+
+            let solution = gs.solve().expect("Generated practice board should be solvable");
+            assert!(solution.iter().any(|m| matches!(m, Move::Single(c) if c.piece == Piece::Metal(2))));
+            solution.replay(&mut gs).unwrap();
+            assert!(gs.is_solved());
+        }
+    }
+    {
+        // odd numbers of elements, each taking a salt
+        let inventory = vec![
+            (Piece::Element(Element::Fire), 3),
+            (Piece::Element(Element::Water), 1),
+            (Piece::Element(Element::Earth), 2),
+            (Piece::Salt, 4),
+            (Piece::Metal(0), 1),
+            (Piece::Vitae, 1),
+            (Piece::Mors, 1),
+        ];
+        let rules = RuleSet::new(2, inventory, 1, Piece::legal_pair).unwrap();
+        for seed in 0..20{
+            let mut gs = generate_with_rules(seed, &rules);
+            let placed = gs.inventory();
+            for (p, n) in rules.inventory(){
+                assert_eq!(placed.get(p), Some(n), "seed {}", seed);
+            }
+            let solution = gs.solve().expect("Generated odd board should be solvable");
+            solution.replay(&mut gs).unwrap();
+            assert!(gs.is_solved());
+        }
+    }
+}
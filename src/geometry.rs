@@ -0,0 +1,130 @@
+use super::game::GameState;
+use super::hex::Hex;
+
+// where the board's cells are on the screen. every conversion between board and screen
+// coordinates goes through this, so a board of any size or position only needs a different
+// geometry. the values are floats so that scaled boards don't collect rounding errors.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoardGeometry{
+    pub origin: (f64, f64), // top left corner of the marble at (0, 0)
+    pub x_step: (f64, f64), // from one cell to the next along x
+    pub y_step: (f64, f64), // and along y
+    pub marble_size: f64
+}
+
+impl BoardGeometry{
+    // the game at 1440x900: each marble is 52 pixels by 52 pixels, the horizontal offset in
+    // a row is 66 and the vertical offset between rows is 57
+    pub const REFERENCE: BoardGeometry = BoardGeometry{
+        origin: (456.0, 675.0),
+        x_step: (66.0, 0.0),
+        y_step: (33.0, -57.0),
+        marble_size: 52.0
+    };
+
+    // the geometry of a screenshot that is this one scaled by scale and then moved by offset
+    pub fn scaled(&self, scale: f64, offset: (f64, f64)) -> BoardGeometry{
+        self.stretched((scale, scale), offset)
+    }
+
+    // the same, but scaled by scale.0 across and scale.1 down. marbles are still read as
+    // squares, so they get the size of the square with the same area.
+    pub fn stretched(&self, scale: (f64, f64), offset: (f64, f64)) -> BoardGeometry{
+        BoardGeometry{
+            origin: (self.origin.0 * scale.0 + offset.0, self.origin.1 * scale.1 + offset.1),
+            x_step: (self.x_step.0 * scale.0, self.x_step.1 * scale.1),
+            y_step: (self.y_step.0 * scale.0, self.y_step.1 * scale.1),
+            marble_size: self.marble_size * (scale.0 * scale.1).sqrt()
+        }
+    }
+
+    fn corner(&self, xi: usize, yi: usize) -> (f64, f64){
+        let (xi, yi) = (xi as f64, yi as f64);
+        (
+            self.origin.0 + self.x_step.0*xi + self.y_step.0*yi,
+            self.origin.1 + self.x_step.1*xi + self.y_step.1*yi
+        )
+    }
+
+    // converts board coordinates to screen coordinates (top left corner)
+    pub fn cell_to_screen(&self, xi: usize, yi: usize) -> (u32, u32){
+        let (x, y) = self.corner(xi, yi);
+        (x.round().max(0.0) as u32, y.round().max(0.0) as u32)
+    }
+
+    // converts board coordinates to screen coordinates (approximate center of marble)
+    pub fn cell_center(&self, xi: usize, yi: usize) -> (u32, u32){
+        let (x, y) = self.corner(xi, yi);
+        let half = self.marble_size / 2.0;
+        ((x + half).round().max(0.0) as u32, (y + half).round().max(0.0) as u32)
+    }
+
+    // the width and height of a marble in whole pixels
+    pub fn marble_pixels(&self) -> u32{
+        self.marble_size.round().max(1.0) as u32
+    }
+
+    // the cell whose hexagon the point (x, y) is in, if it is on the board
+    pub fn screen_to_cell(&self, x: f64, y: f64) -> Option<(usize, usize)>{
+        // how far the point is from the middle of marble (0, 0) in steps of x and y
+        let half = self.marble_size / 2.0;
+        let (dx, dy) = (x - self.origin.0 - half, y - self.origin.1 - half);
+        let det = self.x_step.0*self.y_step.1 - self.y_step.0*self.x_step.1;
+        if det.abs() < 1e-9{
+            return None;
+        }
+        let xi = (dx*self.y_step.1 - dy*self.y_step.0) / det;
+        let yi = (dy*self.x_step.0 - dx*self.x_step.1) / det;
+
+        Hex::round(xi - 5.0, yi - 5.0).to_index()
+    }
+}
+
+pub fn test(){
+    {
+        // the same numbers as the reader always used
+        let g = BoardGeometry::REFERENCE;
+        assert_eq!(g.cell_to_screen(0, 5), (621, 390));
+        assert_eq!(g.cell_to_screen(10, 0), (1116, 675));
+        assert_eq!(g.cell_center(5, 5), (977, 416));
+        assert_eq!(g.marble_pixels(), 52);
+        assert_eq!(g.scaled(1.0, (0.0, 0.0)), g);
+    }
+    {
+        for g in [BoardGeometry::REFERENCE, BoardGeometry::REFERENCE.scaled(1.5, (0.0, 0.0)), BoardGeometry::REFERENCE.scaled(0.6, (40.0, -12.5))]{
+            for xi in 0..11{
+                for yi in 0..11{
+                    if !GameState::on_board(xi, yi){
+                        continue;
+                    }
+                    let (x, y) = g.cell_center(xi, yi);
+                    assert_eq!(g.screen_to_cell(x as f64, y as f64), Some((xi, yi)));
+                    // anywhere on the marble counts
+                    let r = g.marble_size * 0.4;
+                    for (ox, oy) in [(r, 0.0), (-r, 0.0), (0.0, r), (0.0, -r)]{
+                        assert_eq!(g.screen_to_cell(x as f64 + ox, y as f64 + oy), Some((xi, yi)));
+                    }
+                }
+            }
+            // off the board to the left, and in the corner of the screen
+            let (x, y) = g.cell_center(0, 5);
+            assert_eq!(g.screen_to_cell(x as f64 - g.x_step.0, y as f64), None);
+            assert_eq!(g.screen_to_cell(0.0, 0.0), None);
+        }
+    }
+    {
+        let g = BoardGeometry::REFERENCE.scaled(0.5, (100.0, 20.0));
+        assert_eq!(g.cell_to_screen(0, 0), (328, 358));
+        assert_eq!(g.marble_pixels(), 26);
+        assert_eq!(g.cell_center(0, 0), (341, 371));
+        assert_eq!(BoardGeometry::REFERENCE.stretched((0.5, 0.5), (100.0, 20.0)), g);
+
+        // a board squashed to 80% of its height keeps its columns
+        let g = BoardGeometry::REFERENCE.stretched((1.0, 0.8), (0.0, 0.0));
+        assert_eq!(g.cell_to_screen(10, 0), (1116, 540));
+        assert_eq!(g.y_step, (33.0, -57.0 * 0.8));
+        assert!((g.marble_size - 52.0 * 0.8f64.sqrt()).abs() < 1e-9);
+        let (x, y) = g.cell_center(3, 7);
+        assert_eq!(g.screen_to_cell(x as f64, y as f64), Some((3, 7)));
+    }
+}
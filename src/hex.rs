@@ -0,0 +1,274 @@
+use std::ops::Add;
+use std::ops::Mul;
+use std::ops::Sub;
+
+// a cell of the hex grid in axial coordinates, with the middle of the board at (0, 0).
+// q runs along the board's x axis (to the right) and r along its y axis (up and to the
+// right), so board[x][y] is the hex (x - 5, y - 5). the third cube coordinate is s = -q - r.
+#[derive(PartialEq, Copy, Clone, Debug, Eq, Hash, PartialOrd, Ord)]
+pub struct Hex{
+    pub q: i32,
+    pub r: i32
+}
+
+// cells up to this far from the middle are on the board, making a hexagon of side 6
+pub const BOARD_RADIUS: i32 = 5;
+const CENTER: i32 = 5;
+
+// going clockwise from the right. GameState::is_open relies on neighbouring entries being
+// neighbouring directions.
+pub const DIRECTIONS: [Hex; 6] = [
+    Hex{q: 1, r: 0},
+    Hex{q: 1, r: -1},
+    Hex{q: 0, r: -1},
+    Hex{q: -1, r: 0},
+    Hex{q: -1, r: 1},
+    Hex{q: 0, r: 1},
+];
+
+impl Hex{
+    pub const ORIGIN: Hex = Hex{q: 0, r: 0};
+
+    pub const fn new(q: i32, r: i32) -> Hex{
+        Hex{q, r}
+    }
+
+    pub const fn s(&self) -> i32{
+        -self.q - self.r
+    }
+
+    pub const fn from_index(x: usize, y: usize) -> Hex{
+        Hex{q: x as i32 - CENTER, r: y as i32 - CENTER}
+    }
+
+    // the board array index of this hex, if it is on the board
+    pub const fn to_index(&self) -> Option<(usize, usize)>{
+        if self.on_board(){
+            Some(((self.q + CENTER) as usize, (self.r + CENTER) as usize))
+        }else{
+            None
+        }
+    }
+
+    pub const fn on_board(&self) -> bool{
+        self.length() <= BOARD_RADIUS
+    }
+
+    // distance from the middle of the board
+    pub const fn length(&self) -> i32{
+        (self.q.abs() + self.r.abs() + self.s().abs()) / 2
+    }
+
+    // the hex containing the point at fractional axial coordinates (q, r)
+    pub fn round(q: f64, r: f64) -> Hex{
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        // the coordinate that was rounded the furthest is worked out from the other two
+        if dq > dr && dq > ds{
+            rq = -rr - rs;
+        }else if dr > ds{
+            rr = -rq - rs;
+        }
+        Hex{q: rq as i32, r: rr as i32}
+    }
+
+    pub fn distance(&self, other: &Hex) -> i32{
+        (*self - *other).length()
+    }
+
+    pub fn neighbor(&self, direction: usize) -> Hex{
+        *self + DIRECTIONS[direction % 6]
+    }
+
+    // in the same order as DIRECTIONS
+    pub fn neighbors(&self) -> [Hex; 6]{
+        let mut ans = [*self; 6];
+        for (i, d) in DIRECTIONS.iter().enumerate(){
+            ans[i] = *self + *d;
+        }
+        ans
+    }
+
+    // every hex exactly radius away from center, going clockwise
+    pub fn ring(center: Hex, radius: i32) -> Vec<Hex>{
+        if radius <= 0{
+            return vec![center];
+        }
+
+        let mut ans = Vec::new();
+        let mut h = center + DIRECTIONS[4] * radius;
+        for d in DIRECTIONS.iter(){
+            for _ in 0..radius{
+                ans.push(h);
+                h = h + *d;
+            }
+        }
+        ans
+    }
+
+    // every hex at most radius away from center, nearest first
+    pub fn range(center: Hex, radius: i32) -> Vec<Hex>{
+        (0..=radius).flat_map(|r| Hex::ring(center, r)).collect()
+    }
+
+    // every hex on the board, nearest to the middle first
+    pub fn board() -> Vec<Hex>{
+        Hex::range(Hex::ORIGIN, BOARD_RADIUS)
+    }
+
+    // rotates about the middle of the board by steps * 60 degrees counterclockwise
+    pub fn rotate(&self, steps: i32) -> Hex{
+        let mut h = *self;
+        for _ in 0..steps.rem_euclid(6){
+            h = Hex{q: -h.r, r: -h.s()};
+        }
+        h
+    }
+
+    // mirrors top to bottom across the horizontal line through the middle of the board
+    pub fn reflect(&self) -> Hex{
+        Hex{q: -self.s(), r: -self.r}
+    }
+}
+
+// one of the 12 symmetries of the board: an optional reflection followed by a rotation
+#[derive(PartialEq, Copy, Clone, Debug, Eq, Hash)]
+pub struct Symmetry{
+    pub reflect: bool,
+    pub rotation: i32 // in steps of 60 degrees counterclockwise, 0 to 5
+}
+
+impl Symmetry{
+    pub const IDENTITY: Symmetry = Symmetry{reflect: false, rotation: 0};
+
+    pub fn new(reflect: bool, rotation: i32) -> Symmetry{
+        Symmetry{reflect, rotation: rotation.rem_euclid(6)}
+    }
+
+    pub fn all() -> Vec<Symmetry>{
+        let mut ans = Vec::new();
+        for reflect in [false, true]{
+            for rotation in 0..6{
+                ans.push(Symmetry{reflect, rotation});
+            }
+        }
+        ans
+    }
+
+    pub fn apply(&self, h: Hex) -> Hex{
+        if self.reflect{
+            h.reflect().rotate(self.rotation)
+        }else{
+            h.rotate(self.rotation)
+        }
+    }
+
+    // reflecting then rotating is its own inverse, since reflecting reverses the rotation
+    pub fn inverse(&self) -> Symmetry{
+        if self.reflect{
+            *self
+        }else{
+            Symmetry::new(false, -self.rotation)
+        }
+    }
+}
+
+impl Add for Hex{
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex{
+        Hex{q: self.q + other.q, r: self.r + other.r}
+    }
+}
+
+impl Sub for Hex{
+    type Output = Hex;
+
+    fn sub(self, other: Hex) -> Hex{
+        Hex{q: self.q - other.q, r: self.r - other.r}
+    }
+}
+
+impl Mul<i32> for Hex{
+    type Output = Hex;
+
+    fn mul(self, k: i32) -> Hex{
+        Hex{q: self.q * k, r: self.r * k}
+    }
+}
+
+pub fn test(){
+    {
+        let h = Hex::new(2, -1);
+        assert_eq!(h.s(), -1);
+        assert_eq!(h.length(), 2);
+        assert_eq!(h.neighbors().len(), 6);
+        for n in h.neighbors().iter(){
+            assert_eq!(h.distance(n), 1);
+        }
+        assert_eq!(Hex::new(-3, 0).distance(&Hex::new(2, 1)), 6);
+        assert_eq!(Hex::new(5, -5).distance(&Hex::new(-5, 5)), 10);
+
+        assert_eq!(Hex::round(2.1, -0.9), Hex::new(2, -1));
+        assert_eq!(Hex::round(-0.2, 0.3), Hex::ORIGIN);
+        assert_eq!(Hex::round(0.6, -0.6), Hex::new(1, -1));
+        // rounding q and r on their own would give (0, 0)
+        assert_eq!(Hex::round(0.3, 0.45), Hex::new(0, 1));
+    }
+    {
+        // the board is the same set of cells as the old x + y bounds
+        for x in 0..11{
+            for y in 0..11{
+                let h = Hex::from_index(x, y);
+                let old = (x+y >= 5) && (x+y <= 15);
+                assert_eq!(h.on_board(), old);
+                assert_eq!(h.to_index().is_some(), old);
+                if old{
+                    assert_eq!(h.to_index(), Some((x, y)));
+                }
+            }
+        }
+        assert_eq!(Hex::from_index(5, 5), Hex::ORIGIN);
+        assert!(!Hex::new(6, 0).on_board());
+
+        assert_eq!(Hex::ring(Hex::ORIGIN, 0), vec![Hex::ORIGIN]);
+        for r in 1..6{
+            let ring = Hex::ring(Hex::ORIGIN, r);
+            assert_eq!(ring.len() as i32, 6*r);
+            assert!(ring.iter().all(|h| h.length() == r));
+            for i in 0..ring.len(){
+                assert_eq!(ring[i].distance(&ring[(i+1) % ring.len()]), 1);
+            }
+        }
+        assert_eq!(Hex::board().len(), 91);
+        assert_eq!(Hex::range(Hex::new(5, 0), 1).len(), 7);
+    }
+    {
+        let h = Hex::new(3, -1);
+        assert_eq!(Hex::new(1, 0).rotate(1), Hex::new(0, 1));
+        assert_eq!(Hex::new(0, 1).rotate(1), Hex::new(-1, 1));
+        assert_eq!(h.rotate(6), h);
+        assert_eq!(h.rotate(-1), h.rotate(5));
+        assert_eq!(h.rotate(3), Hex::new(-3, 1));
+        assert_eq!(h.reflect().reflect(), h);
+        assert_eq!(Hex::new(1, 0).reflect(), Hex::new(1, 0));
+        assert_eq!(Hex::new(0, 1).reflect(), Hex::new(1, -1));
+        for k in 0..6{
+            assert_eq!(h.rotate(k).length(), h.length());
+            assert_eq!(h.rotate(k).distance(&Hex::new(1, 1).rotate(k)), h.distance(&Hex::new(1, 1)));
+            assert_eq!(h.reflect().rotate(k).length(), h.length());
+        }
+    }
+    {
+        assert_eq!(Symmetry::all().len(), 12);
+        for sym in Symmetry::all(){
+            for h in Hex::board(){
+                let moved = sym.apply(h);
+                assert!(moved.on_board());
+                assert_eq!(sym.inverse().apply(moved), h);
+            }
+        }
+        assert_eq!(Symmetry::IDENTITY.apply(Hex::new(2, 3)), Hex::new(2, 3));
+    }
+}
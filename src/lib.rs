@@ -0,0 +1,18 @@
+// everything the bot in main.rs and the tools in src/bin share
+pub mod screenshot;
+pub mod game;
+pub mod transposition;
+pub mod bitboard;
+pub mod hex;
+pub mod rules;
+pub mod generator;
+pub mod analysis;
+pub mod board_code;
+pub mod render;
+pub mod assignment;
+pub mod templates;
+pub mod geometry;
+pub mod locate;
+pub mod game_reader;
+pub mod image_manipulation;
+pub mod console;
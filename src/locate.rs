@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use image;
+use image::DynamicImage;
+use image::GenericImageView;
+use image::imageops;
+use image::imageops::FilterType;
+
+use super::geometry::BoardGeometry;
+use super::templates;
+
+// finds the board in a screenshot of any size by matching it against the empty board from
+// the 1440x900 reference screenshot. it first tries where the reference has the board, and
+// otherwise searches a small copy of the screenshot at one scale for both directions. then
+// the fit is nudged, on bigger copies, a pixel or a small step of either scale at a time for
+// as long as that improves it, so a window stretched out of shape is still fitted.
+
+// the part of EmptyBoard.png matched against: every cell plus some of the frame around them
+const REFERENCE_AREA: (u32, u32, u32, u32) = (581, 65, 792, 702);
+// matches worse than this (out of 1) are taken to mean there is no board
+const MIN_SCORE: f64 = 0.5;
+// the smallest board looked for, relative to the reference
+const MIN_SCALE: f64 = 0.3;
+// how wide the copies of the screenshot are for the search and for the first nudges
+const SEARCH_WIDTH: f64 = 120.0;
+const NUDGE_WIDTH: f64 = 480.0;
+// nudging stops after this many steps even if it is still improving
+const MAX_NUDGES: usize = 200;
+
+// a grayscale image as floats, with running sums for quick window statistics
+struct Gray{
+    width: usize,
+    height: usize,
+    data: Vec<f64>,
+    sums: Vec<f64>, // sums[y*(width+1) + x] is the total of everything above and left of (x, y)
+    squares: Vec<f64>
+}
+
+impl Gray{
+    fn new(im: &DynamicImage) -> Gray{
+        let luma = im.to_luma8();
+        let data = luma.as_raw().iter().map(|v| *v as f64).collect();
+        Gray::from_data(luma.width() as usize, luma.height() as usize, data)
+    }
+
+    fn from_data(width: usize, height: usize, data: Vec<f64>) -> Gray{
+        let mut sums = vec![0.0; (width + 1) * (height + 1)];
+        let mut squares = vec![0.0; (width + 1) * (height + 1)];
+        for y in 0..height{
+            for x in 0..width{
+                let v = data[y*width + x];
+                let i = (y + 1)*(width + 1) + x + 1;
+                sums[i] = v + sums[i - 1] + sums[i - width - 1] - sums[i - width - 2];
+                squares[i] = v*v + squares[i - 1] + squares[i - width - 1] - squares[i - width - 2];
+            }
+        }
+
+        Gray{width, height, data, sums, squares}
+    }
+
+    // a copy width by height, with each pixel the average of the ones it covers. growing
+    // repeats pixels, which is fine for the few percent the refining goes past full size.
+    fn resized(&self, width: usize, height: usize) -> Gray{
+        let (width, height) = (width.max(1), height.max(1));
+        let stride = self.width + 1;
+        let mut data = Vec::with_capacity(width * height);
+        for ty in 0..height{
+            let y0 = ty * self.height / height;
+            let y1 = ((ty + 1) * self.height / height).max(y0 + 1);
+            for tx in 0..width{
+                let x0 = tx * self.width / width;
+                let x1 = ((tx + 1) * self.width / width).max(x0 + 1);
+                let total = Gray::window_total(&self.sums, stride, x0, y0, x1 - x0, y1 - y0);
+                data.push(total / ((x1 - x0) * (y1 - y0)) as f64);
+            }
+        }
+        Gray::from_data(width, height, data)
+    }
+
+    fn window_total(table: &[f64], stride: usize, x: usize, y: usize, w: usize, h: usize) -> f64{
+        table[(y + h)*stride + x + w] - table[y*stride + x + w] - table[(y + h)*stride + x] + table[y*stride + x]
+    }
+
+    // normalized cross correlation of template against the window with its top left at (x, y)
+    fn correlation(&self, template: &Template, x: usize, y: usize) -> f64{
+        let (w, h) = (template.width, template.height);
+        let n = (w * h) as f64;
+        let stride = self.width + 1;
+        let total = Gray::window_total(&self.sums, stride, x, y, w, h);
+        let squares = Gray::window_total(&self.squares, stride, x, y, w, h);
+        let spread = (squares - total*total/n).max(0.0).sqrt();
+        if spread < 1e-6 || template.spread < 1e-6{
+            return 0.0;
+        }
+
+        let mut dot = 0.0;
+        for ty in 0..h{
+            let row = &self.data[((y + ty)*self.width + x)..((y + ty)*self.width + x + w)];
+            let trow = &template.data[(ty*w)..((ty + 1)*w)];
+            for (a, b) in row.iter().zip(trow.iter()){
+                dot += a * b;
+            }
+        }
+        dot / (spread * template.spread)
+    }
+}
+
+// the reference board resized to some size, with its mean taken out
+struct Template{
+    width: usize,
+    height: usize,
+    data: Vec<f64>,
+    spread: f64
+}
+
+impl Template{
+    fn new(reference: &Gray, width: usize, height: usize) -> Template{
+        let gray = reference.resized(width, height);
+        let mean = gray.data.iter().sum::<f64>() / gray.data.len() as f64;
+        let data: Vec<f64> = gray.data.iter().map(|v| v - mean).collect();
+        let spread = data.iter().map(|v| v*v).sum::<f64>().sqrt();
+        Template{width: gray.width, height: gray.height, data, spread}
+    }
+}
+
+// where the reference area sits in a screenshot: how much it is scaled across and down, and
+// where its middle is in full size pixels
+#[derive(Copy, Clone, Debug)]
+struct Fit{
+    score: f64,
+    scale: (f64, f64),
+    centre: (f64, f64)
+}
+
+// scores fits against one copy of the screenshot, shrunk by shrink, keeping every template
+// it makes
+struct Scorer<'a>{
+    screen: &'a Gray,
+    reference: &'a Gray,
+    shrink: f64,
+    templates: HashMap<(usize, usize), Template>
+}
+
+impl<'a> Scorer<'a>{
+    fn new(screen: &'a Gray, reference: &'a Gray, shrink: f64) -> Scorer<'a>{
+        Scorer{screen, reference, shrink, templates: HashMap::new()}
+    }
+
+    // None if a board of that scale and centre doesn't fit in the screenshot
+    fn score(&mut self, scale: (f64, f64), centre: (f64, f64)) -> Option<f64>{
+        let width = (self.reference.width as f64 * scale.0 * self.shrink).round() as usize;
+        let height = (self.reference.height as f64 * scale.1 * self.shrink).round() as usize;
+        let x = (centre.0 * self.shrink - width as f64 / 2.0).round();
+        let y = (centre.1 * self.shrink - height as f64 / 2.0).round();
+        if width < 8 || height < 8 || x < 0.0 || y < 0.0{
+            return None;
+        }
+        let (x, y) = (x as usize, y as usize);
+        if x + width > self.screen.width || y + height > self.screen.height{
+            return None;
+        }
+
+        let reference = self.reference;
+        let template = self.templates.entry((width, height)).or_insert_with(|| Template::new(reference, width, height));
+        Some(self.screen.correlation(template, x, y))
+    }
+
+    // the best fit with the same scale both ways anywhere in the screenshot
+    fn search(&mut self, scales: &[f64]) -> Option<Fit>{
+        let mut best: Option<Fit> = None;
+        for scale in scales{
+            let width = (self.reference.width as f64 * scale * self.shrink).round() as usize;
+            let height = (self.reference.height as f64 * scale * self.shrink).round() as usize;
+            if width < 8 || height < 8 || width > self.screen.width || height > self.screen.height{
+                continue;
+            }
+            let template = Template::new(self.reference, width, height);
+            for y in 0..=(self.screen.height - height){
+                for x in 0..=(self.screen.width - width){
+                    let score = self.screen.correlation(&template, x, y);
+                    if best.is_none_or(|b| score > b.score){
+                        let centre = ((x as f64 + width as f64 / 2.0) / self.shrink, (y as f64 + height as f64 / 2.0) / self.shrink);
+                        best = Some(Fit{score, scale: (*scale, *scale), centre});
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    // moves fit by a pixel of this copy, or scales it by step across, down or both, for as
+    // long as any of that improves the score
+    fn refine(&mut self, fit: Fit, step: f64) -> Fit{
+        let score = self.score(fit.scale, fit.centre).unwrap_or(f64::NEG_INFINITY);
+        let mut best = Fit{score, ..fit};
+        let pixel = 1.0 / self.shrink;
+        for _ in 0..MAX_NUDGES{
+            let ((sx, sy), (cx, cy)) = (best.scale, best.centre);
+            let mut nudges = vec![
+                ((sx * step, sy), (cx, cy)), ((sx / step, sy), (cx, cy)),
+                ((sx, sy * step), (cx, cy)), ((sx, sy / step), (cx, cy)),
+                ((sx * step, sy * step), (cx, cy)), ((sx / step, sy / step), (cx, cy)),
+            ];
+            for dx in -1..=1{
+                for dy in -1..=1{
+                    if (dx, dy) != (0, 0){
+                        nudges.push(((sx, sy), (cx + dx as f64 * pixel, cy + dy as f64 * pixel)));
+                    }
+                }
+            }
+
+            let mut next = best;
+            for (scale, centre) in nudges{
+                if let Some(score) = self.score(scale, centre){
+                    if score > next.score{
+                        next = Fit{score, scale, centre};
+                    }
+                }
+            }
+            if next.score <= best.score{
+                break;
+            }
+            best = next;
+        }
+        best
+    }
+}
+
+pub fn locate_board(im: &DynamicImage) -> Result<BoardGeometry, String>{
+    locate_board_with(im, &templates::templates()?.empty_board)
+}
+
+// finds where the board of empty_board (a 1440x900 reference screenshot) is in im
+pub fn locate_board_with(im: &DynamicImage, empty_board: &DynamicImage) -> Result<BoardGeometry, String>{
+    let (x0, y0, w0, h0) = REFERENCE_AREA;
+    let (x0, y0, w0, h0) = (x0 as f64, y0 as f64, w0 as f64, h0 as f64);
+    let reference = Gray::new(&empty_board.crop_imm(REFERENCE_AREA.0, REFERENCE_AREA.1, REFERENCE_AREA.2, REFERENCE_AREA.3));
+    let (width, height) = (im.width() as f64, im.height() as f64);
+    let max_scale = (width / w0).min(height / h0);
+    if max_scale < MIN_SCALE{
+        return Err(format!("A {}x{} image is too small to hold a board", im.width(), im.height()));
+    }
+
+    let screen = Gray::new(im);
+    let mut full = Scorer::new(&screen, &reference, 1.0);
+    let reference_fit = Fit{score: 0.0, scale: (1.0, 1.0), centre: (x0 + w0 / 2.0, y0 + h0 / 2.0)};
+    let start = match full.score(reference_fit.scale, reference_fit.centre){
+        // a screenshot of the game at the reference size only needs the last few nudges
+        Some(score) if score > MIN_SCORE => reference_fit,
+        _ => {
+            let copy = |w: f64| -> (Gray, f64){
+                let shrink = (w / width).min(1.0);
+                (screen.resized((width * shrink).round() as usize, (height * shrink).round() as usize), shrink)
+            };
+            let mut scales = Vec::new();
+            let mut s = MIN_SCALE;
+            while s <= max_scale{
+                scales.push(s);
+                s *= 1.04;
+            }
+            let (small, shrink) = copy(SEARCH_WIDTH);
+            let found = Scorer::new(&small, &reference, shrink).search(&scales)
+                .ok_or_else(|| String::from("Couldn't fit the board into the image"))?;
+            let (middle, shrink) = copy(NUDGE_WIDTH);
+            Scorer::new(&middle, &reference, shrink).refine(found, 1.01)
+        },
+    };
+
+    let fit = full.refine(start, 1.0025);
+    if fit.score < MIN_SCORE{
+        return Err(format!("Couldn't find the board (best match was only {:.2})", fit.score));
+    }
+    let (sx, sy) = fit.scale;
+    let corner = (fit.centre.0 - w0 * sx / 2.0, fit.centre.1 - h0 * sy / 2.0);
+    Ok(BoardGeometry::REFERENCE.stretched(fit.scale, (corner.0 - x0 * sx, corner.1 - y0 * sy)))
+}
+
+pub fn test(){
+    let empty_board = image::open("images/EmptyBoard.png").unwrap();
+    {
+        // the reference screenshots are where the reference says
+        for path in ["images/EmptyBoard.png", "images/Game1.png", "images/Game3.png"]{
+            let im = image::open(path).unwrap();
+            let g = locate_board_with(&im, &empty_board).unwrap();
+            assert!((g.marble_size - 52.0).abs() < 0.5, "{}: {:?}", path, g);
+            let (x, y) = g.cell_to_screen(0, 0);
+            assert!((x as i32 - 456).abs() <= 2 && (y as i32 - 675).abs() <= 2, "{}: {:?}", path, g);
+        }
+    }
+    {
+        // a smaller window somewhere on a bigger screen
+        let game = image::open("images/Game1.png").unwrap();
+        let small = imageops::resize(&game.to_rgba8(), 1080, 675, FilterType::Triangle);
+        let mut screen = image::RgbaImage::from_pixel(1920, 1080, image::Rgba([30, 30, 40, 255]));
+        imageops::overlay(&mut screen, &small, 300, 200);
+        let g = locate_board_with(&DynamicImage::ImageRgba8(screen), &empty_board).unwrap();
+        assert!((g.marble_size - 39.0).abs() < 0.5, "{:?}", g);
+        let (x, y) = g.cell_to_screen(0, 0);
+        let expected = (300.0 + 456.0 * 0.75, 200.0 + 675.0 * 0.75);
+        assert!((x as f64 - expected.0).abs() <= 3.0 && (y as f64 - expected.1).abs() <= 3.0, "{:?}", g);
+    }
+    {
+        // a window squashed to 90% of its height, on a bigger screen
+        let game = image::open("images/Game1.png").unwrap();
+        let squashed = imageops::resize(&game.to_rgba8(), 1440, 810, FilterType::Triangle);
+        let mut screen = image::RgbaImage::from_pixel(1600, 1000, image::Rgba([30, 30, 40, 255]));
+        imageops::overlay(&mut screen, &squashed, 80, 60);
+        let g = locate_board_with(&DynamicImage::ImageRgba8(screen), &empty_board).unwrap();
+        let expected = BoardGeometry::REFERENCE.stretched((1.0, 0.9), (80.0, 60.0));
+        for (xi, yi) in [(0, 5), (10, 5), (5, 0), (5, 10), (0, 0), (10, 10)]{
+            let (x, y) = g.cell_center(xi, yi);
+            let (ex, ey) = expected.cell_center(xi, yi);
+            assert!((x as i32 - ex as i32).abs() <= 2 && (y as i32 - ey as i32).abs() <= 2, "({}, {}): {:?}", xi, yi, g);
+        }
+    }
+    {
+        let blank = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1440, 900, image::Rgba([90, 90, 90, 255])));
+        assert!(locate_board_with(&blank, &empty_board).is_err());
+        let tiny = DynamicImage::ImageRgba8(image::RgbaImage::new(100, 100));
+        assert!(locate_board_with(&tiny, &empty_board).is_err());
+    }
+}
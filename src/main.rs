@@ -2,10 +2,7 @@ use std::thread;
 use std::time;
 use std::io;
 
-mod screenshot;
-mod game;
-mod game_reader;
-mod image_manipulation;
+use auto_sigmar::*;
 
 fn main() {
     println!("Program will begin playing in 5 seconds.");
@@ -16,15 +13,63 @@ fn main() {
     }
 
     let screen_image = screenshot::get_screen_image();
-    let mut game_state = game_reader::read_board(&screen_image);
+    let geometry = match locate::locate_board(&screen_image){
+        Ok(geometry) => geometry,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
+    let reading = match game_reader::read_board(&screen_image, &geometry, &rules::RuleSet::standard()){
+        Ok(reading) => reading,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        }
+    };
+
+    // a misread piece usually shows up as a failed click halfway through, so check first
+    let uncertain = reading.uncertain(game_reader::UNCERTAIN_MARGIN);
+    if !uncertain.is_empty(){
+        reading.state.print();
+        println!("These cells were hard to read:");
+        for c in uncertain.iter(){
+            println!("  {}", c);
+        }
+        println!("Play anyway? [y/N]");
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer).expect("Couldn't read input");
+        if answer.trim() != "y"{
+            return;
+        }
+    }
+
+    let mut game_state = reading.state;
     let solution = game_state.solve();
     match solution {
-        Some(s) => game_reader::perform_solution(&s),
-        None => {println!("Failed to find a solution; read boardstate:"); game_state.print()}
+        Some(s) => game_reader::perform_solution(&s, &geometry),
+        None => {
+            println!("Failed to find a solution; read boardstate:");
+            game_state.print();
+            println!("Best partial clearing:");
+            print!("{}", game_state.best_partial_clearing(analysis::ClearingGoal::FewestPieces));
+        }
     };
 
     // game::test();
+    // bitboard::test();
+    // hex::test();
+    // geometry::test();
+    // rules::test();
+    // generator::test();
+    // analysis::test();
+    // board_code::test();
+    // render::test();
     // screenshot::test();
+    // assignment::test();
+    // templates::test();
+    // locate::test();
     // game_reader::test();
     // game_reader::mousetest();
+    // console::test();
 }
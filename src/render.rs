@@ -0,0 +1,157 @@
+use super::game::Cell;
+use super::game::Element;
+use super::game::GameState;
+use super::game::Move;
+use super::game::Piece;
+
+// draws boards for a terminal, in the same slanted layout as GameState's Display but with
+// ANSI colours and coordinates. each row starts with its y, and the x of each diagonal
+// (going down and to the left) is written just past its top end.
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const REVERSE: &str = "\x1b[7m";
+
+// room for the row labels
+const MARGIN: usize = 3;
+
+fn color(piece: &Piece, gold: bool) -> &'static str{
+    match piece{
+        Piece::Element(Element::Fire) => "\x1b[31m",
+        Piece::Element(Element::Water) => "\x1b[34m",
+        Piece::Element(Element::Earth) => "\x1b[32m",
+        Piece::Element(Element::Air) => "\x1b[36m",
+        Piece::Salt => "\x1b[37m",
+        Piece::Quicksilver => "\x1b[97m",
+        Piece::Vitae => "\x1b[95m",
+        Piece::Mors => "\x1b[90m",
+        Piece::Metal(_) if gold => "\x1b[93m",
+        Piece::Metal(_) => "\x1b[33m",
+    }
+}
+
+// a line of output that keeps track of how wide it looks, escape codes aside
+struct Line{
+    text: String,
+    width: usize
+}
+
+impl Line{
+    fn new() -> Line{
+        Line{text: String::new(), width: 0}
+    }
+
+    fn push(&mut self, column: usize, s: &str, style: &str){
+        while self.width < column{
+            self.text.push(' ');
+            self.width += 1;
+        }
+        self.text.push_str(style);
+        self.text.push_str(s);
+        self.text.push_str(RESET);
+        self.width += s.chars().count();
+    }
+}
+
+fn column(x: usize, y: usize) -> usize{
+    MARGIN + 2*x + y - 5
+}
+
+// open pieces are bold and locked ones dim. the cells of marked, if given, are drawn in
+// reverse video.
+pub fn render(gs: &GameState, marked: Option<&Move>) -> String{
+    let marked_cells: Vec<(usize, usize)> = match marked{
+        Some(m) => m.cells().iter().map(|c| (c.x, c.y)).collect(),
+        None => Vec::new(),
+    };
+
+    let mut lines = Vec::new();
+    let mut top = Line::new();
+    for x in 0..=5{
+        top.push(column(x, 11), &x.to_string(), DIM);
+    }
+    lines.push(top);
+
+    for y in (0..11).rev(){
+        let mut line = Line::new();
+        line.push(0, &format!("{:>2}", y), DIM);
+        for x in 0..11{
+            if !GameState::on_board(x, y){
+                continue;
+            }
+            match gs.get_piece(x, y){
+                Some(p) => {
+                    let style = if marked_cells.contains(&(x, y)){
+                        REVERSE.to_string() + BOLD
+                    }else if gs.is_open(x, y){
+                        String::from(BOLD)
+                    }else{
+                        String::from(DIM)
+                    };
+                    line.push(column(x, y), &p.tochar(), &(style + color(p, gs.rules().is_gold(p))));
+                },
+                None => line.push(column(x, y), ".", DIM),
+            }
+        }
+        // the diagonals past the top row's end are labelled on the right of the upper rows
+        if y > 5{
+            let x = 16 - y;
+            line.push(column(x, y), &x.to_string(), DIM);
+        }
+        lines.push(line);
+    }
+
+    lines.iter().map(|l| l.text.clone() + "\n").collect()
+}
+
+// the text with the escape codes taken out
+pub fn strip_ansi(s: &str) -> String{
+    let mut ans = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next(){
+        if c == '\x1b'{
+            for d in chars.by_ref(){
+                if d.is_ascii_alphabetic(){
+                    break;
+                }
+            }
+        }else{
+            ans.push(c);
+        }
+    }
+    ans
+}
+
+pub fn test(){
+    {
+        let gs = GameState::example();
+        let plain = strip_ansi(&render(&gs, None));
+        let lines: Vec<&str> = plain.lines().collect();
+        assert_eq!(lines.len(), 12);
+        assert_eq!(lines[0].trim_end(), "         0 1 2 3 4 5");
+        assert_eq!(lines[1].trim_end(), "10      W . . . . F 6");
+        assert_eq!(lines[6].trim_end(), " 5 A E V F . 5 . A M Q A");
+        assert_eq!(lines[11].trim_end(), " 0      E . . . . A");
+
+        // apart from the labels, it is the same picture as Display
+        let text = gs.to_string();
+        for (drawn, row) in lines[1..].iter().zip(text.lines()){
+            assert!(drawn[MARGIN..].starts_with(row));
+        }
+    }
+    {
+        let gs = GameState::example();
+        let plain = render(&gs, None);
+        let action = Move::Pair(
+            Cell::new(0, 5, Piece::Element(Element::Air)),
+            Cell::new(10, 0, Piece::Element(Element::Air)),
+        );
+        let marked = render(&gs, Some(&action));
+        assert_eq!(strip_ansi(&plain), strip_ansi(&marked));
+        assert_eq!(marked.matches(REVERSE).count(), 2);
+        assert!(plain.matches(BOLD).count() >= gs.open_pieces().len());
+        // the locked metals and covered pieces are dimmed
+        assert!(plain.contains(&(String::from(DIM) + color(&Piece::Metal(3), false) + "3")));
+    }
+}
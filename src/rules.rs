@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use super::bitboard::BitGameState;
+use super::game::Cell;
+use super::game::Element;
+use super::game::GameState;
+use super::game::Move;
+use super::game::Piece;
+use super::game::GOLD;
+use super::hex::Hex;
+use super::hex::BOARD_RADIUS;
+
+// the pieces on a board of the real game
+pub const STANDARD_INVENTORY: [(Piece, usize); 14] = [
+    (Piece::Element(Element::Fire), 8),
+    (Piece::Element(Element::Water), 8),
+    (Piece::Element(Element::Earth), 8),
+    (Piece::Element(Element::Air), 8),
+    (Piece::Salt, 4),
+    (Piece::Metal(0), 1),
+    (Piece::Metal(1), 1),
+    (Piece::Metal(2), 1),
+    (Piece::Metal(3), 1),
+    (Piece::Metal(4), 1),
+    (Piece::Metal(5), 1),
+    (Piece::Quicksilver, 5),
+    (Piece::Vitae, 4),
+    (Piece::Mors, 4),
+];
+
+// Piece::index (and so the bitboard and the board code) only has room for this many metals
+pub const MAX_METALS: u8 = GOLD + 1;
+
+// what a board is allowed to look like: how big it is, which pieces go on it, and what
+// pairs with what. boards are still kept in the 11x11 array, so smaller boards just use
+// the cells nearest the middle.
+#[derive(Clone, Debug)]
+pub struct RuleSet{
+    radius: i32,
+    inventory: Vec<(Piece, usize)>,
+    metals: u8, // metals in the chain, Metal(0) first. the last one is gold.
+    pairs: fn(&Piece, &Piece) -> bool, // never asked about gold, which is removed on its own
+    // whether pairs agrees with the real game's on every piece in the inventory. only then
+    // do counting arguments like salt parity say anything about whether a board can be cleared.
+    standard_pairs: bool
+}
+
+impl RuleSet{
+    pub fn standard() -> RuleSet{
+        RuleSet{
+            radius: BOARD_RADIUS,
+            inventory: STANDARD_INVENTORY.to_vec(),
+            metals: MAX_METALS,
+            pairs: Piece::legal_pair,
+            standard_pairs: true
+        }
+    }
+
+    pub fn new(radius: i32, inventory: Vec<(Piece, usize)>, metals: u8,
+               pairs: fn(&Piece, &Piece) -> bool) -> Result<RuleSet, String>{
+        if !(1..=BOARD_RADIUS).contains(&radius){
+            return Err(format!("Board radius must be between 1 and {}, not {}", BOARD_RADIUS, radius));
+        }
+        if !(1..=MAX_METALS).contains(&metals){
+            return Err(format!("Metal chain must be between 1 and {} long, not {}", MAX_METALS, metals));
+        }
+
+        let mut counts = HashMap::new();
+        for (p, n) in inventory.iter(){
+            if counts.insert(*p, *n).is_some(){
+                return Err(format!("{:?} is in the inventory twice", p));
+            }
+        }
+        for m in 0..MAX_METALS{
+            let expected = if m < metals{ 1 }else{ 0 };
+            if *counts.get(&Piece::Metal(m)).unwrap_or(&0) != expected{
+                return Err(format!("The inventory needs exactly {} of metal {} for a chain of {}", expected, m, metals));
+            }
+        }
+        if let Some((p, _)) = inventory.iter().find(|(p, _)| matches!(p, Piece::Metal(m) if *m >= MAX_METALS)){
+            return Err(format!("{:?} is past the end of the metal chain", p));
+        }
+
+        let gold = Piece::Metal(metals - 1);
+        let standard_pairs = inventory.iter().filter(|(p, _)| *p != gold).all(|(a, _)| {
+            inventory.iter().filter(|(p, _)| *p != gold).all(|(b, _)| pairs(a, b) == Piece::legal_pair(a, b))
+        });
+        let rules = RuleSet{radius, inventory, metals, pairs, standard_pairs};
+        let pieces: usize = rules.inventory.iter().map(|(_, n)| *n).sum();
+        let cells = Hex::range(Hex::ORIGIN, radius).len();
+        if pieces > cells{
+            return Err(format!("{} pieces don't fit on a board of {} cells", pieces, cells));
+        }
+
+        // and under the real game's pairs they have to be able to come off: every element with
+        // an odd count needs a salt with the rest of the salt going in pairs, vitae go with
+        // mors, and every metal but gold needs a quicksilver. other pairs are up to the caller.
+        if !rules.standard_pairs{
+            return Ok(rules);
+        }
+        let count = |p: Piece| *counts.get(&p).unwrap_or(&0);
+        let salt = count(Piece::Salt);
+        let odd_elements = [Element::Water, Element::Fire, Element::Earth, Element::Air].iter()
+            .filter(|e| count(Piece::Element(**e)) % 2 == 1)
+            .count();
+        if odd_elements > salt || (salt - odd_elements) % 2 == 1{
+            return Err(format!("{} salt can't pair off {} elements with an odd count and then itself", salt, odd_elements));
+        }
+        if count(Piece::Vitae) != count(Piece::Mors){
+            return Err(format!("The inventory has {} vitae but {} mors", count(Piece::Vitae), count(Piece::Mors)));
+        }
+        if count(Piece::Quicksilver) != (metals - 1) as usize{
+            return Err(format!("A chain of {} metals needs {} quicksilver, not {}", metals, metals - 1, count(Piece::Quicksilver)));
+        }
+
+        Ok(rules)
+    }
+
+    pub fn radius(&self) -> i32{
+        self.radius
+    }
+
+    // in a fixed order, so anything built from it (like generated boards) is repeatable
+    pub fn inventory(&self) -> &[(Piece, usize)]{
+        &self.inventory
+    }
+
+    // how many of a piece a full board has
+    pub fn count(&self, piece: &Piece) -> usize{
+        self.inventory.iter().find(|(p, _)| p == piece).map_or(0, |(_, n)| *n)
+    }
+
+    pub fn metals(&self) -> u8{
+        self.metals
+    }
+
+    pub fn gold(&self) -> u8{
+        self.metals - 1
+    }
+
+    pub fn is_gold(&self, piece: &Piece) -> bool{
+        *piece == Piece::Metal(self.gold())
+    }
+
+    pub fn has_standard_pairs(&self) -> bool{
+        self.standard_pairs
+    }
+
+    // the piece c stands for in a text board, if these rules use it
+    pub fn piece_from_char(&self, c: char) -> Option<Piece>{
+        Piece::from_char(c).filter(|p| self.count(p) > 0)
+    }
+
+    pub fn legal_pair(&self, a: &Piece, b: &Piece) -> bool{
+        !self.is_gold(a) && !self.is_gold(b) && (self.pairs)(a, b)
+    }
+
+    pub fn on_board(&self, x: usize, y: usize) -> bool{
+        x < 11 && y < 11 && Hex::from_index(x, y).length() <= self.radius
+    }
+
+    // every cell of the board, nearest to the middle first
+    pub fn cells(&self) -> Vec<Hex>{
+        Hex::range(Hex::ORIGIN, self.radius)
+    }
+}
+
+impl Default for RuleSet{
+    fn default() -> RuleSet{
+        RuleSet::standard()
+    }
+}
+
+pub fn test(){
+    {
+        let rules = RuleSet::standard();
+        assert_eq!(rules.gold(), GOLD);
+        assert_eq!(rules.cells().len(), 91);
+        assert_eq!(rules.count(&Piece::Salt), 4);
+        assert!(!rules.legal_pair(&Piece::Metal(GOLD), &Piece::Quicksilver));
+        assert!(rules.legal_pair(&Piece::Metal(0), &Piece::Quicksilver));
+
+        let salt_only = |_: &Piece, _: &Piece| false;
+        assert!(RuleSet::new(0, Vec::new(), 1, salt_only).is_err());
+        assert!(RuleSet::new(6, Vec::new(), 1, salt_only).is_err());
+        assert!(RuleSet::new(2, vec![(Piece::Metal(0), 1)], 0, salt_only).is_err());
+        assert!(RuleSet::new(2, vec![(Piece::Metal(0), 1)], 7, salt_only).is_err());
+        assert!(RuleSet::new(2, vec![(Piece::Metal(1), 1)], 1, salt_only).is_err());
+        assert!(RuleSet::new(2, vec![(Piece::Metal(0), 1), (Piece::Salt, 2), (Piece::Salt, 2)], 1, salt_only).is_err());
+        assert!(RuleSet::new(1, vec![(Piece::Metal(0), 1), (Piece::Salt, 7)], 1, salt_only).is_err());
+
+        // inventories that can't be cleared: an odd fire with the only salt, vitae without
+        // enough mors, and a metal without a quicksilver
+        let unclearable = vec![
+            (Piece::Element(Element::Fire), 3),
+            (Piece::Salt, 1),
+            (Piece::Metal(0), 1),
+            (Piece::Vitae, 1),
+            (Piece::Mors, 2),
+        ];
+        assert!(RuleSet::new(2, unclearable.clone(), 1, Piece::legal_pair).unwrap_err().contains("vitae"));
+        let mut odd = unclearable.clone();
+        odd[4] = (Piece::Mors, 1);
+        assert!(RuleSet::new(2, odd.clone(), 1, Piece::legal_pair).is_ok());
+        odd.push((Piece::Element(Element::Water), 1));
+        assert!(RuleSet::new(2, odd, 1, Piece::legal_pair).unwrap_err().contains("salt"));
+        assert!(RuleSet::new(2, vec![(Piece::Metal(0), 1), (Piece::Metal(1), 1)], 2, Piece::legal_pair).unwrap_err().contains("quicksilver"));
+
+        // a one metal chain is just gold
+        let tiny = RuleSet::new(1, vec![(Piece::Metal(0), 1), (Piece::Salt, 6)], 1, salt_only).unwrap();
+        assert!(tiny.is_gold(&Piece::Metal(0)));
+        assert!(!tiny.legal_pair(&Piece::Salt, &Piece::Salt));
+        assert!(tiny.on_board(5, 5) && tiny.on_board(6, 4) && !tiny.on_board(7, 5));
+        assert!(RuleSet::standard().has_standard_pairs() && !tiny.has_standard_pairs());
+        assert_eq!(tiny.piece_from_char('S'), Some(Piece::Salt));
+        assert_eq!(tiny.piece_from_char('F'), None);
+    }
+    {
+        // vitae that also pair with each other. counting vitae against mors would call this
+        // board stuck, so it has to be left to the search
+        let doubles = |a: &Piece, b: &Piece| a == b || a.legal_pair(b);
+        let rules = RuleSet::new(2, vec![(Piece::Metal(0), 1), (Piece::Vitae, 2)], 1, doubles).unwrap();
+        assert!(!rules.has_standard_pairs());
+        let mut gs = GameState::with_rules(rules);
+        gs.set_piece(Some(Piece::Metal(0)), 5, 5).unwrap();
+        gs.set_piece(Some(Piece::Vitae), 3, 5).unwrap();
+        gs.set_piece(Some(Piece::Vitae), 7, 5).unwrap();
+        assert!(gs.could_be_solvable());
+        let solution = gs.solve().expect("Two vitae should pair under these rules");
+        assert_eq!(solution.len(), 2);
+        assert_eq!(gs.count_solutions(true), 1);
+    }
+    {
+        // with a chain of three, metal 2 is gold and comes off alone
+        let inventory = vec![
+            (Piece::Metal(0), 1),
+            (Piece::Metal(1), 1),
+            (Piece::Metal(2), 1),
+            (Piece::Quicksilver, 2),
+        ];
+        let rules = RuleSet::new(2, inventory, 3, Piece::legal_pair).unwrap();
+        let mut gs = GameState::with_rules(rules.clone());
+        assert!(gs.set_piece(Some(Piece::Salt), 8, 5).is_err());
+        gs.set_piece(Some(Piece::Metal(2)), 5, 5).unwrap();
+        gs.set_piece(Some(Piece::Metal(0)), 3, 5).unwrap();
+        gs.set_piece(Some(Piece::Metal(1)), 7, 5).unwrap();
+        gs.set_piece(Some(Piece::Quicksilver), 5, 3).unwrap();
+        gs.set_piece(Some(Piece::Quicksilver), 5, 7).unwrap();
+        assert!(gs.could_be_solvable());
+
+        // text boards are read under the rules they are for
+        let text = gs.to_string();
+        let parsed = GameState::parse_with_rules(&text, rules.clone()).unwrap();
+        assert_eq!(parsed.zobrist_hash(), gs.zobrist_hash());
+        assert!(GameState::parse_with_rules(&format!("{}metals taken: 3\n", text), rules.clone()).is_ok());
+        assert!(GameState::parse_with_rules(&format!("{}metals taken: 4\n", text), rules.clone()).is_err());
+        let misread = |piece: Piece, x: usize, y: usize| -> String{
+            let mut standard = GameState::new();
+            standard.set_piece(Some(piece), x, y).unwrap();
+            GameState::parse_with_rules(&standard.to_string(), rules.clone()).err().expect("Board should not parse").message
+        };
+        assert!(misread(Piece::Metal(4), 5, 5).contains("gold is 2"));
+        assert!(misread(Piece::Element(Element::Fire), 5, 5).contains("isn't used"));
+        assert!(misread(Piece::Quicksilver, 0, 5).contains("off the board"));
+
+        let mut bgs = BitGameState::from_game_state(&gs);
+        let solution = gs.solve().expect("Short chain should be solvable");
+        assert_eq!(bgs.solve(), Some(solution.clone()));
+        assert_eq!(solution.len(), 3);
+        assert_eq!(solution.moves()[2], Move::Single(Cell::new(5, 5, Piece::Metal(2))));
+
+        // the same pieces under the standard rules are stuck on metal 2
+        let mut standard = gs.clone();
+        standard.set_rules(RuleSet::standard()).unwrap();
+        assert!(!standard.could_be_solvable());
+        assert!(standard.solve().is_none());
+        assert!(gs.set_rules(RuleSet::new(1, vec![(Piece::Metal(0), 1)], 1, Piece::legal_pair).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use std::env;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use image;
+use image::DynamicImage;
+use image::GenericImageView;
+
+use super::game::Element;
+use super::game::Piece;
+
+// the images the reader compares screenshots against, cut from a 1440x900 screenshot and
+// built into the binary so it runs from any directory. if AUTO_SIGMAR_IMAGES names a
+// directory laid out like images/ (EmptyBoard.png and Pieces/*.png), any of those files it
+// has are used instead of the built in ones.
+
+pub const OVERRIDE_VAR: &str = "AUTO_SIGMAR_IMAGES";
+
+const EMPTY_BOARD: (&str, &[u8]) = ("EmptyBoard.png", include_bytes!("../images/EmptyBoard.png"));
+
+// the template for each piece, and for an empty cell
+const PIECES: [(Option<Piece>, &str, &[u8]); 15] = [
+    (Some(Piece::Element(Element::Fire)), "Pieces/Fire.png", include_bytes!("../images/Pieces/Fire.png")),
+    (Some(Piece::Element(Element::Water)), "Pieces/Water.png", include_bytes!("../images/Pieces/Water.png")),
+    (Some(Piece::Element(Element::Earth)), "Pieces/Earth.png", include_bytes!("../images/Pieces/Earth.png")),
+    (Some(Piece::Element(Element::Air)), "Pieces/Air.png", include_bytes!("../images/Pieces/Air.png")),
+    (Some(Piece::Salt), "Pieces/Salt.png", include_bytes!("../images/Pieces/Salt.png")),
+    (Some(Piece::Metal(0)), "Pieces/Metal1.png", include_bytes!("../images/Pieces/Metal1.png")),
+    (Some(Piece::Metal(1)), "Pieces/Metal2.png", include_bytes!("../images/Pieces/Metal2.png")),
+    (Some(Piece::Metal(2)), "Pieces/Metal3.png", include_bytes!("../images/Pieces/Metal3.png")),
+    (Some(Piece::Metal(3)), "Pieces/Metal4.png", include_bytes!("../images/Pieces/Metal4.png")),
+    (Some(Piece::Metal(4)), "Pieces/Metal5.png", include_bytes!("../images/Pieces/Metal5.png")),
+    (Some(Piece::Metal(5)), "Pieces/Metal6.png", include_bytes!("../images/Pieces/Metal6.png")),
+    (Some(Piece::Quicksilver), "Pieces/Quicksilver.png", include_bytes!("../images/Pieces/Quicksilver.png")),
+    (Some(Piece::Vitae), "Pieces/Vitae.png", include_bytes!("../images/Pieces/Vitae.png")),
+    (Some(Piece::Mors), "Pieces/Mors.png", include_bytes!("../images/Pieces/Mors.png")),
+    (None, "Pieces/Empty.png", include_bytes!("../images/Pieces/Empty.png")),
+];
+
+pub struct Templates{
+    pub empty_board: DynamicImage,
+    pub pieces: Vec<(Option<Piece>, DynamicImage)>
+}
+
+// the file from dir if there is one, otherwise the built in copy
+fn load(dir: Option<&Path>, name: &str, bytes: &[u8]) -> Result<DynamicImage, String>{
+    if let Some(path) = dir.map(|d| d.join(name)).filter(|p| p.exists()){
+        return image::open(&path).map_err(|e| format!("Couldn't open {}: {}", path.display(), e));
+    }
+    image::load_from_memory(bytes).map_err(|e| format!("Couldn't decode the built in {}: {}", name, e))
+}
+
+impl Templates{
+    pub fn embedded() -> Result<Templates, String>{
+        Templates::load(None)
+    }
+
+    pub fn from_dir(dir: &Path) -> Result<Templates, String>{
+        if !dir.is_dir(){
+            return Err(format!("{} is not a directory", dir.display()));
+        }
+        Templates::load(Some(dir))
+    }
+
+    fn load(dir: Option<&Path>) -> Result<Templates, String>{
+        let empty_board = load(dir, EMPTY_BOARD.0, EMPTY_BOARD.1)?;
+        let mut pieces = Vec::new();
+        for (piece, name, bytes) in PIECES.iter(){
+            let im = load(dir, name, bytes)?;
+            if im.dimensions() != (52, 52){
+                return Err(format!("{} is {}x{}, but templates are 52x52", name, im.width(), im.height()));
+            }
+            pieces.push((*piece, im));
+        }
+        Ok(Templates{empty_board, pieces})
+    }
+
+    pub fn piece(&self, piece: Option<Piece>) -> Option<&DynamicImage>{
+        self.pieces.iter().find(|(p, _)| *p == piece).map(|(_, im)| im)
+    }
+}
+
+static TEMPLATES: OnceLock<Result<Templates, String>> = OnceLock::new();
+
+// decoded the first time they are needed and kept for the rest of the run
+pub fn templates() -> Result<&'static Templates, String>{
+    TEMPLATES.get_or_init(|| match env::var_os(OVERRIDE_VAR){
+        Some(dir) => Templates::from_dir(Path::new(&dir)).map_err(|e| format!("{}: {}", OVERRIDE_VAR, e)),
+        None => Templates::embedded(),
+    }).as_ref().map_err(|e| e.clone())
+}
+
+pub fn test(){
+    {
+        let built_in = Templates::embedded().unwrap();
+        assert_eq!(built_in.empty_board.dimensions(), (1440, 900));
+        assert_eq!(built_in.pieces.len(), 15);
+        assert!(built_in.piece(None).is_some());
+        assert!(built_in.piece(Some(Piece::Metal(5))).is_some());
+        assert!(built_in.piece(Some(Piece::Metal(6))).is_none());
+
+        // the same pictures as the files they were built from
+        let files = Templates::from_dir(Path::new("images")).unwrap();
+        for ((p, a), (q, b)) in built_in.pieces.iter().zip(files.pieces.iter()){
+            assert_eq!(p, q);
+            assert_eq!(a.to_rgba8().as_raw(), b.to_rgba8().as_raw());
+        }
+
+        let cached = templates().unwrap() as *const Templates;
+        assert_eq!(templates().unwrap() as *const Templates, cached);
+    }
+    {
+        // a directory with only some of the files overrides just those
+        let dir = env::temp_dir().join("auto_sigmar_templates_test");
+        std::fs::create_dir_all(dir.join("Pieces")).unwrap();
+        let salt = Templates::embedded().unwrap().piece(Some(Piece::Salt)).unwrap().clone();
+        salt.save(dir.join("Pieces/Fire.png")).unwrap();
+        let overridden = Templates::from_dir(&dir).unwrap();
+        let fire = overridden.piece(Some(Piece::Element(Element::Fire))).unwrap();
+        assert_eq!(fire.to_rgba8().as_raw(), salt.to_rgba8().as_raw());
+        assert_eq!(overridden.empty_board.dimensions(), (1440, 900));
+
+        // templates have to be the right size
+        salt.crop_imm(0, 0, 40, 40).save(dir.join("Pieces/Fire.png")).unwrap();
+        assert!(Templates::from_dir(&dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(Templates::from_dir(&dir).is_err());
+    }
+}
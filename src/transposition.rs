@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+use super::game::Move;
+use super::game::Piece;
+
+// rough cost of one entry: the u64 in the set (plus hashbrown's control byte and slack)
+// and the u64 in the eviction queue
+const BYTES_PER_ENTRY: usize = 32;
+// and of one count: the u64 key and u128 count in the map (with slack) and the key in the queue
+const BYTES_PER_COUNT: usize = 64;
+
+pub const DEFAULT_MEMORY_BUDGET: usize = 64 * 1024 * 1024;
+
+// splitmix64, used to derive the zobrist keys without storing a key table
+fn mix(mut z: u64) -> u64{
+    z = z.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// the key xored into a position's hash when `piece` sits at (x, y)
+pub fn piece_key(piece: &Piece, x: usize, y: usize) -> u64{
+    mix(((x * 11 + y) as u64) << 16 | piece.index() as u64)
+}
+
+// the key xored into a position's hash for its metals_taken
+pub fn metals_key(metals_taken: u8) -> u64{
+    mix(0xffff_0000_0000 | metals_taken as u64)
+}
+
+fn move_key(action: &Move) -> u64{
+    match action{
+        Move::Single(a) => mix(piece_key(&a.piece, a.x, a.y)),
+        Move::Pair(a, b) => mix(piece_key(&a.piece, a.x, a.y) ^ piece_key(&b.piece, b.x, b.y).rotate_left(32)),
+    }
+}
+
+// the key xored into a position's hash for the moves asleep there, in any order
+pub fn sleep_key(sleep: &[Move]) -> u64{
+    mix(sleep.iter().fold(0, |k, m| k ^ move_key(m)))
+}
+
+// remembers positions the solver has already proven to be dead ends, keyed by zobrist hash.
+// once the memory budget is used up the oldest entries are evicted first.
+// a hash collision can (very rarely) make the solver skip a solvable position.
+pub struct TranspositionTable{
+    dead: HashSet<u64>,
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl TranspositionTable{
+    // memory_budget is in bytes
+    pub fn new(memory_budget: usize) -> TranspositionTable{
+        TranspositionTable{
+            dead: HashSet::new(),
+            order: VecDeque::new(),
+            capacity: (memory_budget / BYTES_PER_ENTRY).max(1),
+        }
+    }
+
+    pub fn is_dead(&self, hash: u64) -> bool{
+        self.dead.contains(&hash)
+    }
+
+    pub fn mark_dead(&mut self, hash: u64){
+        if self.dead.insert(hash){
+            self.order.push_back(hash);
+            while self.order.len() > self.capacity{
+                if let Some(old) = self.order.pop_front(){
+                    self.dead.remove(&old);
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize{
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.order.is_empty()
+    }
+}
+
+impl Default for TranspositionTable{
+    fn default() -> TranspositionTable{
+        TranspositionTable::new(DEFAULT_MEMORY_BUDGET)
+    }
+}
+
+// remembers how many solutions count_solutions found from a position (and sleep set), keyed
+// by hash. like TranspositionTable, the oldest entries are evicted once the budget is used
+// up, and a hash collision can (very rarely) give a wrong count.
+pub struct CountTable{
+    counts: HashMap<u64, u128>,
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl CountTable{
+    // memory_budget is in bytes
+    pub fn new(memory_budget: usize) -> CountTable{
+        CountTable{
+            counts: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: (memory_budget / BYTES_PER_COUNT).max(1),
+        }
+    }
+
+    pub fn get(&self, key: u64) -> Option<u128>{
+        self.counts.get(&key).copied()
+    }
+
+    pub fn insert(&mut self, key: u64, count: u128){
+        if self.counts.insert(key, count).is_none(){
+            self.order.push_back(key);
+            while self.order.len() > self.capacity{
+                if let Some(old) = self.order.pop_front(){
+                    self.counts.remove(&old);
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize{
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool{
+        self.order.is_empty()
+    }
+}
+
+impl Default for CountTable{
+    fn default() -> CountTable{
+        CountTable::new(DEFAULT_MEMORY_BUDGET)
+    }
+}